@@ -1,5 +1,5 @@
-use std::path::PathBuf;
 use clap::{Parser, Subcommand};
+use crate::library::interval::Interval;
 
 /// Represents the command-line arguments structure.
 #[derive(Parser, Debug)]
@@ -10,45 +10,178 @@ pub(crate) struct Args {
     pub(crate) command: Commands,
 }
 
+/// The routines a command acts on, named by registry name, title or file path, or all of them.
+#[derive(clap::Args, Debug)]
+pub(crate) struct RoutineSelector {
+    /// Name or title of the routine, or the path to its file.
+    #[clap(required_unless_present = "all")]
+    pub(crate) routine: Option<String>,
+
+    /// Act on every registered routine.
+    #[clap(long, conflicts_with = "routine")]
+    pub(crate) all: bool,
+}
+
 /// Enum representing the available commands in the CLI.
 #[derive(Subcommand, Debug)]
 pub(crate) enum Commands {
     /// Prints detailed information about a routine.
     #[clap(about = "Display information about a routine.", long_about = None, name = "info")]
     Info {
-        /// The path to the routine to retrieve information from.
-        routine: PathBuf,
+        /// The routine to retrieve information from.
+        #[clap(flatten)]
+        routines: RoutineSelector,
+
+        /// Show sensitive values such as passwords in plain text, after confirmation.
+        #[clap(long)]
+        reveal: bool,
+
+        /// Skip the confirmation asked by --reveal.
+        #[clap(long, short, requires = "reveal")]
+        yes: bool,
     },
 
     /// Prepares a routine's Git repository for further actions (e.g., pushing).
     #[clap(about = "Prepare a routine's Git repository for subsequent actions.", long_about = None, name = "stage")]
     Stage {
-        /// Path to the routine repository to be staged.
-        routine: PathBuf,
+        /// The routine to be staged.
+        #[clap(flatten)]
+        routines: RoutineSelector,
 
         /// Optional commit message; if none is provided, current timestamp is used.
+        #[clap(long, short)]
         message: Option<String>,
     },
 
     /// Creates a new routine file under the name, "name", defaulting to current dir name
     #[clap(about = "Init and register a new routine.", long_about = None, name = "init")]
     Init {
-        name: Option<String>
+        /// Name of the routine, defaults to the name of the current directory.
+        name: Option<String>,
+
+        /// Git remote URL to sync the routine with.
+        #[clap(long)]
+        remote: Option<String>,
+
+        /// Git branch to push to and pull from.
+        #[clap(long)]
+        branch: Option<String>,
+
+        /// Commit interval of the routine, e.g. "1d" or a cron expression.
+        #[clap(long)]
+        interval: Option<Interval>,
+
+        /// Pattern of files to exclude, can be repeated.
+        #[clap(long)]
+        exclude: Vec<String>,
+    },
+
+    /// Reads or changes a setting of a routine, keeping the comments of its file.
+    #[clap(about = "Read or change the settings of a routine.", long_about = None, name = "config")]
+    Config {
+        #[clap(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Runs the ACT-CLOCK daemon, throwing routines as their commit intervals pass
+    #[clap(about = "Run the ACT-CLOCK daemon.", long_about = None, name = "clock")]
+    Clock,
+
+    /// Lists every registered routine along with its health
+    #[clap(about = "List the registered routines and their health.", long_about = None, name = "list")]
+    List {
+        /// Only list routines whose last sync failed or that cannot be read.
+        #[clap(long)]
+        failing: bool,
+
+        /// Only list routines that are due for a sync.
+        #[clap(long)]
+        due: bool,
+
+        /// Print the routines as JSON rather than as a table.
+        #[clap(long)]
+        json: bool,
     },
 
     /// Sync the routine library and routine folder
     #[clap(about = "Sync the routine library with the routine folder", long_about = None, name = "sync")]
     Sync,
 
+    /// Changes the name a routine is registered under, leaving its file untouched
+    #[clap(about = "Rename a routine in the routine library.", long_about = None, name = "rename")]
+    Rename {
+        /// Name or title of the routine to rename, or the path to its file.
+        routine: String,
+
+        /// New name of the routine.
+        name: String,
+    },
+
     /// Pushes a routine's Git repository to the specified remote.
     #[clap(about = "Push a routine's Git repository to its remote.", long_about = None, name = "throw")]
-    Throw {},
+    Throw {
+        /// The routine to be pushed.
+        #[clap(flatten)]
+        routines: RoutineSelector,
+
+        /// Optional commit message; if none is provided, current timestamp is used.
+        #[clap(long, short)]
+        message: Option<String>,
+    },
 
     /// Pulls a routine's Git repository from the specified remote.
     #[clap(about = "Pull a routine's Git repository from its remote.", long_about = None, name = "catch")]
-    Catch,
+    Catch {
+        /// The routine to be pulled.
+        #[clap(flatten)]
+        routines: RoutineSelector,
+    },
 
     /// Reverts a routine's Git repository to a previous commit.
     #[clap(about = "Revert a routine's Git repository to a previous commit.", long_about = None, name = "wind")]
-    Wind,
+    Wind {
+        /// The routine to be reverted.
+        #[clap(flatten)]
+        routines: RoutineSelector,
+
+        /// Snapshot to restore: a commit id, a date (`YYYY-MM-DD [HH:MM:SS]`) or `N-ago`;
+        /// lists the history when omitted.
+        #[clap(long)]
+        to: Option<String>,
+    },
+}
+
+/// Enum representing the actions of the `config` command.
+#[derive(Subcommand, Debug)]
+pub(crate) enum ConfigAction {
+    /// Prints a setting of a routine, or its whole file.
+    #[clap(about = "Print a setting of a routine.", long_about = None, name = "get")]
+    Get {
+        /// Name or title of the routine to read, or the path to its file.
+        routine: String,
+
+        /// Dotted key of the setting, e.g. `git.remote`; the whole file when omitted.
+        key: Option<String>,
+
+        /// Show sensitive values such as passwords in plain text, after confirmation.
+        #[clap(long)]
+        reveal: bool,
+
+        /// Skip the confirmation asked by --reveal.
+        #[clap(long, short, requires = "reveal")]
+        yes: bool,
+    },
+
+    /// Changes a setting of a routine.
+    #[clap(about = "Change a setting of a routine.", long_about = None, name = "set")]
+    Set {
+        /// Name or title of the routine to change, or the path to its file.
+        routine: String,
+
+        /// Dotted key of the setting, e.g. `git.remote` or `notify.0.kind`.
+        key: String,
+
+        /// New value, read as TOML when possible and as a string otherwise.
+        value: String,
+    },
 }
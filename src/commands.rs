@@ -4,5 +4,60 @@ pub(crate) mod stage;
 pub(crate) mod throw;
 pub(crate) mod wind;
 pub(crate) mod sync;
+pub(crate) mod rename;
 
 pub(crate) mod init;
+pub(crate) mod clock;
+pub(crate) mod list;
+pub(crate) mod config;
+
+use std::error::Error;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::process;
+use crate::cli::RoutineSelector;
+use crate::library::lister::Lister;
+
+/// Resolves the routines a command acts on, by name, title, file path or all of them
+pub(crate) fn select(routines: &RoutineSelector) -> Vec<PathBuf> {
+    Lister::new()
+        .unwrap_or_else(|error| fail(format!("Error loading lister: {}", error)))
+        .select(routines.routine.as_deref(), routines.all)
+        .unwrap_or_else(|error| fail(error))
+}
+
+/// Resolves a single routine, by name, title or file path
+pub(crate) fn resolve(routine: &str) -> PathBuf {
+    Lister::new()
+        .unwrap_or_else(|error| fail(format!("Error loading lister: {}", error)))
+        .resolve(routine)
+        .unwrap_or_else(|error| fail(error))
+}
+
+/// Runs a command on each of the resolved routines, then exits with status 1 if it failed on any
+///
+/// A failing routine is reported as `Error <action> <path>` and does not keep the others from
+/// being processed.
+pub(crate) fn for_each_routine(
+    routine_paths: Vec<PathBuf>,
+    action: &str,
+    mut run: impl FnMut(&PathBuf) -> Result<(), Box<dyn Error>>,
+) {
+    let mut failed = false;
+    for routine_path in routine_paths {
+        if let Err(error) = run(&routine_path) {
+            eprintln!("Error {} {}: {}", action, routine_path.display(), error);
+            failed = true;
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// Prints an error the user can act on, such as an unknown routine, and exits with status 1
+pub(crate) fn fail(error: impl Display) -> ! {
+    eprintln!("{}", error);
+    process::exit(1);
+}
@@ -1,3 +1,30 @@
-pub(crate) fn catch() {
-    todo!();
+use chrono::Utc;
+use std::path::Path;
+use crate::cli::RoutineSelector;
+use crate::commands::{fail, for_each_routine, select};
+use crate::library::routine::Routine;
+use crate::library::state::StateStore;
+
+pub(crate) fn catch(routines: &RoutineSelector) {
+    let store = StateStore::new()
+        .unwrap_or_else(|error| fail(format!("Error opening the state store: {}", error)));
+
+    for_each_routine(select(routines), "catching", |routine_path| {
+        catch_routine(routine_path, &store)
+    });
+}
+
+/// Catches a routine and records the outcome in the state store
+fn catch_routine(
+    routine_path: &Path,
+    store: &StateStore,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let routine = Routine::read(&routine_path.to_path_buf())?;
+    let mut state = store.load(routine_path)?;
+
+    let result = routine.catch();
+    state.record(Utc::now(), result.as_ref().map(|report| report.commit).map_err(|error| &**error));
+    store.save(routine_path, &state)?;
+
+    result.map(|_| ())
 }
@@ -0,0 +1,14 @@
+use crate::commands::fail;
+use crate::library::daemon::{Daemon, SystemClock};
+
+pub(crate) fn clock() {
+    let mut daemon = Daemon::new(SystemClock)
+        .unwrap_or_else(|error| fail(format!("Error loading routines: {}", error)));
+
+    for (path, error) in &daemon.broken {
+        eprintln!("Skipping {}: {}", path.display(), error);
+    }
+
+    println!("ACT-CLOCK started with {} routines", daemon.routines.len());
+    daemon.run();
+}
@@ -0,0 +1,29 @@
+use crate::commands::info::confirm_reveal;
+use crate::commands::{fail, resolve};
+use crate::library::document::RoutineDocument;
+
+pub(crate) fn get(routine: &str, key: &Option<String>, reveal: bool, yes: bool) {
+    let document = RoutineDocument::open(&resolve(routine))
+        .unwrap_or_else(|error| fail(format!("Error reading routine: {}", error)));
+
+    let value = document
+        .get(key.as_deref(), reveal && (yes || confirm_reveal()))
+        .unwrap_or_else(|error| fail(format!("Error reading setting: {}", error)));
+
+    println!("{}", value);
+}
+
+pub(crate) fn set(routine: &str, key: &str, value: &str) {
+    let routine_path = resolve(routine);
+    let mut document = RoutineDocument::open(&routine_path)
+        .unwrap_or_else(|error| fail(format!("Error reading routine: {}", error)));
+
+    document
+        .set(key, value)
+        .unwrap_or_else(|error| fail(format!("Error setting {}: {}", key, error)));
+    document
+        .save()
+        .unwrap_or_else(|error| fail(format!("Error writing routine: {}", error)));
+
+    println!("Set {} in {}", key, routine_path.display());
+}
@@ -1,13 +1,38 @@
+use crate::cli::RoutineSelector;
+use crate::commands::{fail, for_each_routine, select};
 use crate::library::routine::Routine;
-use std::path::{Path, PathBuf};
+use crate::library::state::StateStore;
+use std::io::{self, BufRead, Write};
 
-pub(crate) fn info(routine_path: &PathBuf) {
-    if !routine_path.exists() {
-        println!("Routine not found");
-        return;
+pub(crate) fn info(routines: &RoutineSelector, reveal: bool, yes: bool) {
+    let routine_paths = select(routines);
+    let reveal = reveal && (yes || confirm_reveal());
+
+    let store = StateStore::new()
+        .unwrap_or_else(|error| fail(format!("Error opening the state store: {}", error)));
+
+    for_each_routine(routine_paths, "reading", |routine_path| {
+        if !routine_path.exists() {
+            println!("Routine not found: {}", routine_path.display());
+            return Ok(());
+        }
+
+        let routine = Routine::read(routine_path)?;
+        let state = store.load(routine_path)?;
+        routine.print(&state, reveal);
+        Ok(())
+    });
+}
+
+/// Asks before printing secrets, which would then stay in the terminal's scrollback
+pub(crate) fn confirm_reveal() -> bool {
+    eprint!("Sensitive values will be printed in plain text. Continue? [y/N] ");
+    let _ = io::stderr().flush();
+
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
     }
 
-    Routine::read(&routine_path)
-        .unwrap_or_else(|err| panic!("Error reading routine: {}", err))
-        .print();
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
 }
@@ -1,4 +1,60 @@
+use std::env;
+use crate::commands::fail;
+use crate::library::interval::Interval;
+use crate::library::config::Configuration;
+use crate::library::lister::Lister;
+use crate::library::routine::Routine;
 
-pub(crate) fn init(name: &Option<String>) {
-    todo!()
-}
\ No newline at end of file
+pub(crate) fn init(
+    name: &Option<String>,
+    remote: &Option<String>,
+    branch: &Option<String>,
+    interval: &Option<Interval>,
+    exclude: &[String],
+) {
+    let current_dir = env::current_dir()
+        .unwrap_or_else(|error| fail(format!("Could not get the current directory: {}", error)));
+
+    // Default the routine name to the name of the current directory
+    let name = name.clone().unwrap_or_else(|| {
+        current_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| fail("Could not get the name of the current directory"))
+    });
+
+    let routine_path = Configuration::get_dir()
+        .unwrap_or_else(|error| fail(format!("Could not get the config directory: {}", error)))
+        .join(format!("ACT-{}.toml", name));
+    if routine_path.exists() {
+        fail(format!("Routine already exists: {}", routine_path.display()));
+    }
+
+    let mut routine = Routine::new(name, current_dir.to_string_lossy().to_string());
+    if let Some(remote) = remote {
+        routine.git.remote = remote.clone();
+    }
+    if let Some(branch) = branch {
+        routine.git.branch = branch.clone();
+    }
+    if let Some(interval) = interval {
+        routine.base.interval.commit_interval = interval.clone();
+    }
+    routine.advanced.exclude = exclude.to_vec();
+
+    routine
+        .write(&routine_path)
+        .unwrap_or_else(|error| fail(format!("Error writing routine: {}", error)));
+
+    // Register the new routine in the lister
+    let mut lister = Lister::new()
+        .unwrap_or_else(|error| fail(format!("Error loading lister: {}", error)));
+    let canonical_path = dunce::canonicalize(&routine_path)
+        .unwrap_or_else(|error| fail(format!("Error resolving {}: {}", routine_path.display(), error)));
+    lister.add_file(&canonical_path, None);
+    lister
+        .dump()
+        .unwrap_or_else(|error| fail(format!("Error writing lister: {}", error)));
+
+    println!("Created routine {}", routine_path.display());
+}
@@ -0,0 +1,33 @@
+use chrono::Utc;
+use tabled::{settings::*, Table};
+use crate::commands::fail;
+use crate::library::lister::Lister;
+use crate::library::state::StateStore;
+
+pub(crate) fn list(failing: bool, due: bool, json: bool) {
+    let lister = Lister::new()
+        .unwrap_or_else(|error| fail(format!("Error loading lister: {}", error)));
+    let store = StateStore::new()
+        .unwrap_or_else(|error| fail(format!("Error opening the state store: {}", error)));
+
+    let mut summaries = lister.summaries(&store, Utc::now());
+    summaries.retain(|summary| (!failing || summary.is_failing()) && (!due || summary.due));
+
+    if json {
+        let output = serde_json::to_string_pretty(&summaries)
+            .unwrap_or_else(|error| fail(format!("Error serializing routines: {}", error)));
+        println!("{}", output);
+        return;
+    }
+
+    if summaries.is_empty() {
+        println!("No routines");
+        return;
+    }
+
+    let table = Table::new(summaries)
+        .with(Settings::default().with(Style::rounded()))
+        .to_string();
+
+    println!("{}", table);
+}
@@ -0,0 +1,21 @@
+use crate::commands::fail;
+use crate::library::lister::Lister;
+
+pub(crate) fn rename(routine: &str, name: &str) {
+    let mut lister = Lister::new()
+        .unwrap_or_else(|error| fail(format!("Error loading lister: {}", error)));
+
+    let routine_path = lister
+        .resolve(routine)
+        .unwrap_or_else(|error| fail(error));
+    let routine_path = dunce::canonicalize(&routine_path).unwrap_or(routine_path);
+
+    lister
+        .rename(&routine_path, name)
+        .unwrap_or_else(|error| fail(format!("Error renaming routine: {}", error)));
+    lister
+        .dump()
+        .unwrap_or_else(|error| fail(format!("Error writing lister: {}", error)));
+
+    println!("Renamed {} to {}", routine, name);
+}
@@ -1,11 +1,20 @@
-use std::path::PathBuf;
-use crate::library::routine::Routine;
+use std::process;
+use crate::cli::RoutineSelector;
+use crate::commands::{for_each_routine, select};
+use crate::library::routine::{Routine, StageOutcome};
 
-pub(crate) fn stage(routine_path: &PathBuf, message: &Option<String>) {
-    Routine::read(routine_path)
-        .unwrap_or_else(|error| {
-            panic!("Error reading routine: {}", error);
-        })
-        .stage(message)
-        .expect("Error staging routine");
+/// Exit status of `act stage` when there was nothing to commit
+pub(crate) const NOTHING_TO_SYNC_EXIT_CODE: i32 = 3;
+
+pub(crate) fn stage(routines: &RoutineSelector, message: &Option<String>) {
+    let mut committed = false;
+    for_each_routine(select(routines), "staging", |routine_path| {
+        let outcome = Routine::read(routine_path)?.stage(message)?;
+        committed |= outcome != StageOutcome::Unchanged;
+        Ok(())
+    });
+
+    if !committed {
+        process::exit(NOTHING_TO_SYNC_EXIT_CODE);
+    }
 }
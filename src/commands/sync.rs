@@ -1,6 +1,19 @@
-use crate::library::config;
+use crate::commands::fail;
 use crate::library::lister::Lister;
 
 pub(crate) fn sync() {
-    let result = Lister::new();
+    let mut lister = Lister::new()
+        .unwrap_or_else(|error| fail(format!("Error loading lister: {}", error)));
+
+    // Duplicated titles and broken routines are reported even when nothing changed
+    if lister.changes.is_empty() && lister.changes.has_warnings() {
+        println!("No routines were added, removed or moved, but some need attention:");
+    } else if lister.changes.is_empty() {
+        println!("Routine registry is up to date");
+    }
+    print!("{}", lister.changes);
+
+    lister
+        .dump()
+        .unwrap_or_else(|error| fail(format!("Error writing lister: {}", error)));
 }
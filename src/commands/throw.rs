@@ -1,3 +1,31 @@
-pub(crate) fn throw() {
-    todo!()
+use chrono::Utc;
+use std::path::Path;
+use crate::cli::RoutineSelector;
+use crate::commands::{fail, for_each_routine, select};
+use crate::library::routine::Routine;
+use crate::library::state::StateStore;
+
+pub(crate) fn throw(routines: &RoutineSelector, message: &Option<String>) {
+    let store = StateStore::new()
+        .unwrap_or_else(|error| fail(format!("Error opening the state store: {}", error)));
+
+    for_each_routine(select(routines), "throwing", |routine_path| {
+        throw_routine(routine_path, &store, message)
+    });
+}
+
+/// Throws a routine and records the outcome in the state store
+fn throw_routine(
+    routine_path: &Path,
+    store: &StateStore,
+    message: &Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let routine = Routine::read(&routine_path.to_path_buf())?;
+    let mut state = store.load(routine_path)?;
+
+    let result = routine.throw(message);
+    state.record(Utc::now(), result.as_ref().map(|(_, report)| report.commit).map_err(|error| &**error));
+    store.save(routine_path, &state)?;
+
+    result.map(|_| ())
 }
@@ -1,3 +1,23 @@
-pub(crate) fn wind() {
-    todo!();
+use crate::cli::RoutineSelector;
+use crate::commands::{for_each_routine, select};
+use crate::library::routine::Routine;
+
+pub(crate) fn wind(routines: &RoutineSelector, to: &Option<String>) {
+    let routine_paths = select(routines);
+    let several = routine_paths.len() > 1;
+
+    for_each_routine(routine_paths, "winding", |routine_path| {
+        let routine = Routine::read(routine_path)?;
+
+        // Without a target, list the snapshots that can be wound back to
+        match to {
+            Some(target) => routine.wind(target),
+            None => {
+                if several {
+                    println!("{}", routine.base.title);
+                }
+                routine.print_history()
+            },
+        }
+    });
 }
@@ -2,3 +2,13 @@ pub(crate) mod routine;
 pub(crate) mod daemon;
 pub(crate) mod config;
 pub(crate) mod lister;
+pub(crate) mod interval;
+pub(crate) mod exclude;
+pub(crate) mod crypt;
+pub(crate) mod codec;
+pub(crate) mod secret;
+pub(crate) mod press;
+pub(crate) mod delta;
+pub(crate) mod notify;
+pub(crate) mod state;
+pub(crate) mod document;
@@ -0,0 +1,382 @@
+use git2::{Index, IndexEntry, IndexTime, ObjectType, Oid, Repository, Tree, TreeWalkMode, TreeWalkResult};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::library::crypt::{Cipher, CryptHeader, CRYPT_HEADER_FILE_NAME};
+use crate::library::delta::set_stat;
+use crate::library::press::{pack, unpack, Compression, DEFAULT_THRESHOLD};
+use crate::library::routine::{Compress, Encrypt};
+
+const MODE_FILE: u32 = 0o100644;
+const MODE_EXECUTABLE: u32 = 0o100755;
+const MODE_LINK: u32 = 0o120000;
+
+/// Turns the files of a synced directory into their committed representation and back
+///
+/// Plain routines commit files as they are. Large files are compressed first if the routine
+/// says so, then encrypted routines commit the output of their cipher, along with the header
+/// needed to derive the key again.
+pub(crate) struct Codec {
+    cipher: Option<Cipher>,
+    header: Option<Oid>,
+    press: Compress,
+}
+
+/// A file as found in a working directory or a tree: its content and git file mode
+#[derive(PartialEq)]
+struct FileData {
+    content: Vec<u8>,
+    mode: u32,
+}
+
+impl Codec {
+    /// Returns whether files are committed as they are
+    pub(crate) fn is_plain(&self) -> bool {
+        self.cipher.is_none() && self.press.compression == Compression::Nil
+    }
+
+    /// Describes how files are encoded, so that files encoded differently can be told apart
+    pub(crate) fn fingerprint(&self) -> String {
+        let header = self.header.map_or("plain".to_string(), |header| header.to_string());
+        let threshold = self.press.threshold.unwrap_or(DEFAULT_THRESHOLD);
+
+        format!("{} {} {}", header, threshold, self.press.compression)
+    }
+
+    /// Returns the blob of the header to commit alongside encrypted files
+    pub(crate) fn header(&self) -> Option<Oid> {
+        self.header
+    }
+
+    /// Returns the codec to stage files with
+    ///
+    /// Encrypted repositories keep the header found at HEAD, so the key stays the same as long
+    /// as the password does. A new header is only created the first time files are encrypted.
+    pub(crate) fn for_staging(
+        repo: &Repository,
+        head_tree: Option<&Tree<'_>>,
+        crypt: &Encrypt,
+        press: &Compress,
+    ) -> Result<Self, Box<dyn Error>> {
+        let press = press.clone();
+        if !crypt.encrypt {
+            return Ok(Codec { cipher: None, header: None, press });
+        }
+
+        let password = crypt.password()?;
+        match head_tree.map(|tree| read_header(repo, tree)).transpose()?.flatten() {
+            Some((header, oid)) => {
+                Ok(Codec { cipher: Some(header.unlock(&password)?), header: Some(oid), press })
+            },
+            None => {
+                let (header, cipher) = CryptHeader::create(&password)?;
+                let oid = repo.blob(&header.to_bytes()?)?;
+                Ok(Codec { cipher: Some(cipher), header: Some(oid), press })
+            },
+        }
+    }
+
+    /// Returns the codec to read the files of a committed tree with
+    pub(crate) fn for_tree(
+        repo: &Repository,
+        tree: Option<&Tree<'_>>,
+        crypt: &Encrypt,
+        press: &Compress,
+    ) -> Result<Self, Box<dyn Error>> {
+        let press = press.clone();
+        match tree.map(|tree| read_header(repo, tree)).transpose()?.flatten() {
+            Some((header, oid)) => {
+                let cipher = header.unlock(&crypt.password()?)?;
+                Ok(Codec { cipher: Some(cipher), header: Some(oid), press })
+            },
+            None => Ok(Codec { cipher: None, header: None, press }),
+        }
+    }
+
+    /// Turns the content of the file at `path`, relative to the repository, into what gets
+    /// committed
+    pub(crate) fn encode(&self, content: &[u8], path: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        let threshold = self.press.threshold.unwrap_or(DEFAULT_THRESHOLD);
+        let packed = pack(content, &self.press.compression, threshold)?;
+
+        Ok(match &self.cipher {
+            Some(cipher) => cipher.encrypt(&packed, path.as_bytes()),
+            None => packed,
+        })
+    }
+
+    /// Turns committed data back into the content of the file at `path`
+    pub(crate) fn decode(&self, data: &[u8], path: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        let packed = match &self.cipher {
+            Some(cipher) => cipher.decrypt(data, path.as_bytes())?,
+            None => data.to_vec(),
+        };
+
+        unpack(&packed, self.press.decompression.as_deref())
+    }
+}
+
+/// Reads the encryption header committed at the root of a tree, if any
+fn read_header(repo: &Repository, tree: &Tree<'_>) -> Result<Option<(CryptHeader, Oid)>, Box<dyn Error>> {
+    let Some(entry) = tree.get_name(CRYPT_HEADER_FILE_NAME) else {
+        return Ok(None);
+    };
+
+    let blob = repo.find_blob(entry.id())?;
+    Ok(Some((CryptHeader::parse(blob.content())?, entry.id())))
+}
+
+/// Reads a file from the working directory, `None` if there is none
+fn read_file(path: &Path) -> io::Result<Option<FileData>> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+
+    // Like git, store symbolic links as the path they point to
+    if metadata.file_type().is_symlink() {
+        let target = fs::read_link(path)?;
+        return Ok(Some(FileData {
+            content: target.to_string_lossy().into_owned().into_bytes(),
+            mode: MODE_LINK,
+        }));
+    }
+    if metadata.is_dir() {
+        return Ok(None);
+    }
+
+    Ok(Some(FileData { content: fs::read(path)?, mode: file_mode(&metadata) }))
+}
+
+/// Returns the git file mode of a file, from its metadata as returned by `symlink_metadata`
+pub(crate) fn git_mode(metadata: &fs::Metadata) -> u32 {
+    if metadata.file_type().is_symlink() {
+        MODE_LINK
+    } else {
+        file_mode(metadata)
+    }
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+
+    if metadata.permissions().mode() & 0o111 != 0 {
+        MODE_EXECUTABLE
+    } else {
+        MODE_FILE
+    }
+}
+
+#[cfg(not(unix))]
+fn file_mode(_metadata: &fs::Metadata) -> u32 {
+    MODE_FILE
+}
+
+/// Writes a file to the working directory, replacing whatever is there
+fn write_file(path: &Path, file: &FileData) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Never write through an existing link
+    if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+        fs::remove_file(path)?;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        if file.mode == MODE_LINK {
+            std::os::unix::fs::symlink(String::from_utf8_lossy(&file.content).as_ref(), path)?;
+            return Ok(());
+        }
+
+        fs::write(path, &file.content)?;
+        let permissions = if file.mode == MODE_EXECUTABLE { 0o755 } else { 0o644 };
+        fs::set_permissions(path, fs::Permissions::from_mode(permissions))?;
+    }
+    #[cfg(not(unix))]
+    fs::write(path, &file.content)?;
+
+    Ok(())
+}
+
+/// Removes a file from the working directory, along with the directories it leaves empty
+fn remove_file(root: &Path, path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+        _ => {},
+    }
+
+    let mut parent = path.parent();
+    while let Some(directory) = parent.filter(|directory| *directory != root) {
+        if fs::remove_dir(directory).is_err() {
+            break;
+        }
+        parent = directory.parent();
+    }
+
+    Ok(())
+}
+
+/// Adds a file of the working directory to the index in its committed representation
+///
+/// The entry records the stat data of the file rather than of the blob, for delta syncs.
+pub(crate) fn add_encoded(
+    repo: &Repository,
+    index: &mut Index,
+    root: &Path,
+    relative_path: &Path,
+    codec: &Codec,
+) -> Result<(), Box<dyn Error>> {
+    let path = root.join(relative_path);
+    let metadata = fs::symlink_metadata(&path)?;
+    let file =
+        read_file(&path)?.ok_or_else(|| format!("Could not read {}", relative_path.display()))?;
+    let entry_path = relative_path.to_string_lossy();
+
+    // Link targets are neither secret nor large enough to be worth breaking the links over
+    let data = if file.mode == MODE_LINK {
+        file.content
+    } else {
+        codec.encode(&file.content, &entry_path)?
+    };
+
+    let mut entry = blob_entry(&entry_path, repo.blob(&data)?, file.mode, data.len());
+    set_stat(&mut entry, &metadata);
+    index.add(&entry)?;
+
+    Ok(())
+}
+
+/// Adds an existing blob to the index
+pub(crate) fn add_blob(
+    index: &mut Index,
+    path: &str,
+    id: Oid,
+    mode: u32,
+    size: usize,
+) -> Result<(), Box<dyn Error>> {
+    index.add(&blob_entry(path, id, mode, size))?;
+
+    Ok(())
+}
+
+/// Builds an index entry for a blob, without any stat data
+fn blob_entry(path: &str, id: Oid, mode: u32, size: usize) -> IndexEntry {
+    IndexEntry {
+        ctime: IndexTime::new(0, 0),
+        mtime: IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode,
+        uid: 0,
+        gid: 0,
+        file_size: size as u32,
+        id,
+        flags: path.len().min(0xfff) as u16,
+        flags_extended: 0,
+        path: path.as_bytes().to_vec(),
+    }
+}
+
+/// Lists the files of a tree by path, leaving out the encryption header
+fn tree_files(tree: &Tree<'_>) -> Result<BTreeMap<String, (Oid, u32)>, git2::Error> {
+    let mut files = BTreeMap::new();
+
+    tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+        let path = format!("{}{}", root, entry.name().unwrap_or_default());
+        if entry.kind() == Some(ObjectType::Blob) && path != CRYPT_HEADER_FILE_NAME {
+            files.insert(path, (entry.id(), entry.filemode() as u32));
+        }
+        TreeWalkResult::Ok
+    })?;
+
+    Ok(files)
+}
+
+/// Reads the file of a tree at `path` back into its working directory form
+fn decode_file(
+    repo: &Repository,
+    codec: &Codec,
+    path: &str,
+    (id, mode): (Oid, u32),
+) -> Result<FileData, Box<dyn Error>> {
+    let blob = repo.find_blob(id)?;
+    let content = if mode == MODE_LINK {
+        blob.content().to_vec()
+    } else {
+        codec.decode(blob.content(), path)?
+    };
+
+    Ok(FileData { content, mode })
+}
+
+/// Updates the working directory at `root` from the files of tree `from` to those of `to`
+///
+/// Only files that differ between the two trees are touched, files that are not part of
+/// either tree are left alone. Unless `force` is set, nothing is written if a file to update
+/// was changed locally since `from`, and the conflicting files are reported instead.
+pub(crate) fn restore_tree(
+    repo: &Repository,
+    root: &Path,
+    from: Option<&Tree<'_>>,
+    from_codec: &Codec,
+    to: &Tree<'_>,
+    to_codec: &Codec,
+    force: bool,
+) -> Result<(), Box<dyn Error>> {
+    let from_files = from.map(tree_files).transpose()?.unwrap_or_default();
+    let to_files = tree_files(to)?;
+
+    let changed = from_files
+        .keys()
+        .chain(to_files.keys())
+        .filter(|path| from_files.get(*path) != to_files.get(*path))
+        .collect::<BTreeSet<_>>();
+
+    // Decode everything up front so that a wrong password never leaves a half restored tree
+    let mut updates = Vec::new();
+    let mut conflicts = Vec::new();
+    for path in changed {
+        let target =
+            to_files.get(path).map(|file| decode_file(repo, to_codec, path, *file)).transpose()?;
+
+        if !force {
+            let expected = from_files
+                .get(path)
+                .map(|file| decode_file(repo, from_codec, path, *file))
+                .transpose()?;
+            let local = read_file(&root.join(path))?;
+
+            if local != expected && local != target {
+                conflicts.push(path.clone());
+                continue;
+            }
+        }
+
+        updates.push((path, target));
+    }
+
+    if !conflicts.is_empty() {
+        return Err(format!(
+            "Local changes would be overwritten, commit or remove them first:\n  {}",
+            conflicts.join("\n  ")
+        )
+        .into());
+    }
+
+    for (path, target) in updates {
+        match target {
+            Some(file) => write_file(&root.join(path), &file)?,
+            None => remove_file(root, &root.join(path))?,
+        }
+    }
+
+    Ok(())
+}
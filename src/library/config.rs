@@ -1,20 +1,16 @@
-use std::{path::PathBuf, env, fs, io, error};
+use std::{path::{Path, PathBuf}, fs, io, error};
 use std::collections::HashMap;
-use std::fs::OpenOptions;
+use std::io::Write;
+use tempfile::NamedTempFile;
 
 pub(crate) const CONFIG_FILE_NAME: &str = "config.toml";
 
 pub(crate) struct Configuration;
 
 impl Configuration {
-    /// Find the config directory
+    /// Find the config directory, creating it if needed
     pub(crate) fn get_dir() -> Result<PathBuf, Box<dyn error::Error>> {
-        let home_dir = home::home_dir().ok_or("Could not find home directory.")?;
-
-        let config_dir =
-            home_dir
-                .join(".config")
-                .join("ACT-IV");
+        let config_dir = Self::dir()?;
 
         if let Err(error) = fs::create_dir_all(&config_dir) {
             if error.kind() != io::ErrorKind::AlreadyExists {
@@ -25,30 +21,40 @@ impl Configuration {
         Ok(config_dir)
     }
 
-    /// Write to the global config file for ACT-IV
-    pub(crate) fn write() -> io::Result<()> {
-        let config_file_path =
-            Self::get_dir()
-                .expect("Error getting config directory.")
-                .join(CONFIG_FILE_NAME);
-
-        let file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(config_file_path)
-            .expect("Could not open config file.");
-
-        // write!(file, "Hello, world!")?;
+    /// Path of the config directory, which may not exist yet
+    fn dir() -> Result<PathBuf, Box<dyn error::Error>> {
+        let home_dir = home::home_dir().ok_or("Could not find home directory.")?;
 
-        Ok(())
+        Ok(home_dir.join(".config").join("ACT-IV"))
     }
+
     /// Read from the global config file for ACT-IV
     pub(crate) fn read() -> Result<HashMap<String, String>, Box<dyn error::Error>> {
-        let config_file_path = Self::get_dir()?.join(CONFIG_FILE_NAME);
+        Self::read_from(&Self::dir()?)
+    }
+
+    /// Read from the global config file kept in the given configuration directory
+    pub(crate) fn read_from(config_dir: &Path) -> Result<HashMap<String, String>, Box<dyn error::Error>> {
+        let config_file_path = config_dir.join(CONFIG_FILE_NAME);
         let content = fs::read_to_string(config_file_path)?;
 
         let config: HashMap<String, String> = toml::from_str(&content)?;
         Ok(config)
     }
-}
\ No newline at end of file
+}
+
+/// Replaces the content of a file with a temporary file written next to it, so that readers
+/// never see it half written
+pub(crate) fn write_atomic(path: &Path, content: &str) -> Result<(), Box<dyn error::Error>> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let mut file = NamedTempFile::new_in(dir)?;
+    file.write_all(content.as_bytes())?;
+    file.as_file().sync_all()?;
+    file.persist(path)?;
+
+    Ok(())
+}
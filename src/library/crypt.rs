@@ -0,0 +1,153 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::error::Error;
+use zeroize::Zeroizing;
+
+/// Name of the header committed at the root of encrypted repositories
+pub(crate) const CRYPT_HEADER_FILE_NAME: &str = ".act-crypt";
+
+/// Prefix of every encrypted blob, followed by the nonce and the ciphertext
+const MAGIC: &[u8] = b"ACT-IV\x01";
+
+/// Known plaintext encrypted into the header to detect wrong passwords
+const CHECK_PLAINTEXT: &[u8] = b"ACT-IV password check";
+
+const NONCE_LENGTH: usize = 24;
+const SALT_LENGTH: usize = 16;
+
+/// Parameters needed to derive the key of an encrypted repository, stored in plain text
+///
+/// The salt is generated once per repository so that every device deriving the key from the
+/// same password ends up with the same key.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct CryptHeader {
+    pub(crate) version: u32,
+    pub(crate) kdf: String,
+    pub(crate) memory_cost: u32,
+    pub(crate) time_cost: u32,
+    pub(crate) parallelism: u32,
+    pub(crate) salt: String,
+    pub(crate) check: String,
+}
+
+/// Authenticated cipher for the files of a repository
+///
+/// Encryption is deterministic: the nonce is derived from the plaintext with a keyed hash, so
+/// unchanged files produce unchanged blobs and do not show up as changes in every commit.
+/// Every blob is bound to the path of its file, so blobs cannot be swapped between files.
+pub(crate) struct Cipher {
+    cipher: XChaCha20Poly1305,
+    nonce_key: Zeroizing<Vec<u8>>,
+}
+
+impl CryptHeader {
+    /// Creates the header of a new encrypted repository, along with its cipher
+    pub(crate) fn create(password: &str) -> Result<(Self, Cipher), Box<dyn Error>> {
+        let mut salt = [0u8; SALT_LENGTH];
+        getrandom::getrandom(&mut salt).map_err(|error| error.to_string())?;
+
+        let mut header = CryptHeader {
+            version: 1,
+            kdf: String::from("argon2id"),
+            memory_cost: Params::DEFAULT_M_COST,
+            time_cost: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+            salt: hex::encode(salt),
+            check: String::new(),
+        };
+
+        let cipher = header.derive(password)?;
+        let check = cipher.encrypt(CHECK_PLAINTEXT, CRYPT_HEADER_FILE_NAME.as_bytes());
+        header.check = hex::encode(check);
+
+        Ok((header, cipher))
+    }
+
+    /// Parses a header as committed in a repository
+    pub(crate) fn parse(data: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let header: CryptHeader = toml::from_str(std::str::from_utf8(data)?)?;
+
+        if header.version != 1 || header.kdf != "argon2id" {
+            return Err(format!(
+                "Unsupported encryption header: version {} using {}",
+                header.version, header.kdf
+            )
+            .into());
+        }
+
+        Ok(header)
+    }
+
+    /// Serializes the header to be committed
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(toml::to_string(self)?.into_bytes())
+    }
+
+    /// Derives the cipher from the password, failing if it is not the repository's password
+    pub(crate) fn unlock(&self, password: &str) -> Result<Cipher, Box<dyn Error>> {
+        let cipher = self.derive(password)?;
+
+        match cipher.decrypt(&hex::decode(&self.check)?, CRYPT_HEADER_FILE_NAME.as_bytes()) {
+            Ok(check) if check == CHECK_PLAINTEXT => Ok(cipher),
+            _ => Err("Wrong password for the encrypted repository".into()),
+        }
+    }
+
+    /// Derives the keys with Argon2id, one for the cipher and one for nonces
+    fn derive(&self, password: &str) -> Result<Cipher, Box<dyn Error>> {
+        let params = Params::new(self.memory_cost, self.time_cost, self.parallelism, Some(64))
+            .map_err(|error| error.to_string())?;
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+
+        let mut key = Zeroizing::new(vec![0u8; 64]);
+        argon2
+            .hash_password_into(password.as_bytes(), &hex::decode(&self.salt)?, &mut key)
+            .map_err(|error| error.to_string())?;
+
+        Ok(Cipher {
+            cipher: XChaCha20Poly1305::new_from_slice(&key[..32]).map_err(|error| error.to_string())?,
+            nonce_key: Zeroizing::new(key[32..].to_vec()),
+        })
+    }
+}
+
+impl Cipher {
+    /// Encrypts the data of the file at `path`, the same data always resulting in the same output
+    ///
+    /// The path is authenticated along with the data, so the output only decrypts for that path.
+    pub(crate) fn encrypt(&self, plaintext: &[u8], path: &[u8]) -> Vec<u8> {
+        // The path is part of the nonce too, so the same data at two paths never shares a nonce
+        let mut mac =
+            <Hmac<Sha256> as Mac>::new_from_slice(&self.nonce_key).expect("HMAC accepts any key");
+        mac.update(&(path.len() as u64).to_le_bytes());
+        mac.update(path);
+        mac.update(plaintext);
+        let digest = mac.finalize().into_bytes();
+        let nonce = XNonce::from_slice(&digest[..NONCE_LENGTH]);
+
+        let ciphertext = self
+            .cipher
+            .encrypt(nonce, Payload { msg: plaintext, aad: path })
+            .expect("Encryption cannot fail");
+
+        [MAGIC, nonce.as_slice(), &ciphertext].concat()
+    }
+
+    /// Decrypts data produced by `encrypt` for the file at `path`, failing if it was tampered
+    /// with or belongs to another file
+    pub(crate) fn decrypt(&self, data: &[u8], path: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let data = data.strip_prefix(MAGIC).ok_or("Data is not encrypted by ACT-IV")?;
+        if data.len() < NONCE_LENGTH {
+            return Err("Encrypted data is truncated".into());
+        }
+
+        let (nonce, ciphertext) = data.split_at(NONCE_LENGTH);
+        self.cipher
+            .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad: path })
+            .map_err(|_| "Could not decrypt data, wrong password or corrupted data".into())
+    }
+}
@@ -1 +1,235 @@
+use chrono::{DateTime, Duration, Utc};
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::thread;
+use crate::library::lister::Lister;
+use crate::library::routine::Routine;
+use crate::library::state::{RoutineState, StateStore};
 
+/// Longest time the daemon sleeps before checking its routines again
+pub(crate) const POLL_INTERVAL: Duration = Duration::minutes(1);
+
+/// Time to wait before retrying a routine whose throw failed
+pub(crate) const RETRY_DELAY: Duration = Duration::minutes(5);
+
+/// Source of time for the daemon, allowing tests to control it without sleeping
+pub(crate) trait Clock {
+    /// Returns the current time
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Blocks for the given duration
+    fn sleep(&self, duration: Duration);
+}
+
+/// Clock backed by the system time
+pub(crate) struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration.to_std().unwrap_or_default());
+    }
+}
+
+/// A routine tracked by the daemon along with the file it was read from
+pub(crate) struct Scheduled {
+    pub(crate) path: PathBuf,
+    pub(crate) routine: Routine,
+    pub(crate) state: RoutineState,
+    pub(crate) retry_at: Option<DateTime<Utc>>,
+    /// When the routine is due after the daemon started, until its first sync attempt
+    pub(crate) startup_due: Option<DateTime<Utc>>,
+}
+
+/// The ACT-CLOCK daemon, throwing every routine when its commit interval has passed
+pub(crate) struct Daemon<C: Clock> {
+    pub(crate) clock: C,
+    pub(crate) store: StateStore,
+    pub(crate) routines: Vec<Scheduled>,
+    pub(crate) broken: Vec<(PathBuf, String)>, // Routines left out as they could not be read, with the error
+}
+
+impl Scheduled {
+    /// Tracks a routine from the time the daemon starts
+    ///
+    /// Routines with `sync_on_startup` are due immediately, even when their interval has not
+    /// passed yet.
+    pub(crate) fn new(path: PathBuf, routine: Routine, state: RoutineState, now: DateTime<Utc>) -> Self {
+        let startup_due = routine.advanced.sync_on_startup.then_some(now);
+
+        Scheduled { path, routine, state, retry_at: None, startup_due }
+    }
+
+    /// Returns when the routine should next be thrown
+    ///
+    /// Routines whose schedule has no further runs are never due.
+    pub(crate) fn next_due(&self) -> DateTime<Utc> {
+        let due = self
+            .startup_due
+            .or_else(|| {
+                let interval = &self.routine.base.interval;
+                interval.next_due(self.state.last_sync(interval))
+            })
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+
+        self.retry_at.map_or(due, |retry_at| due.max(retry_at))
+    }
+
+    /// Throws the routine and records the sync in the state store, scheduling a retry on failure
+    fn sync(&mut self, store: &StateStore, now: DateTime<Utc>) -> Result<(), String> {
+        // Having nothing to sync counts as a success just like a new snapshot does
+        let result = self.routine.throw(&None);
+
+        // Record the time the run was scheduled for, as seen by the daemon's clock
+        self.state.record(now, result.as_ref().map(|(_, report)| report.commit).map_err(|error| &**error));
+        let saved = store.save(&self.path, &self.state);
+        let result = result.map(|_| ()).and(saved).map_err(|error| error.to_string());
+
+        self.startup_due = None;
+        self.retry_at = result.is_err().then(|| now + RETRY_DELAY);
+        result
+    }
+
+    /// Syncs the routine like `sync`, turning an unexpected panic into an error retried later
+    ///
+    /// Failures are reported as errors by `sync`; this is only a last resort so that a bug in
+    /// one routine does not stop the others or the daemon.
+    fn sync_guarded(&mut self, store: &StateStore, now: DateTime<Utc>) -> Result<(), String> {
+        panic::catch_unwind(AssertUnwindSafe(|| self.sync(store, now))).unwrap_or_else(|panic| {
+            self.startup_due = None;
+            self.retry_at = Some(now + RETRY_DELAY);
+            Err(format!("The sync panicked: {}", panic_message(&*panic)))
+        })
+    }
+}
+
+impl<C: Clock> Daemon<C> {
+    /// Creates a daemon for every routine registered in the lister
+    pub(crate) fn new(clock: C) -> Result<Self, Box<dyn std::error::Error>> {
+        let lister = Lister::new()?;
+        let paths = lister.get_data().values().cloned().collect::<Vec<_>>();
+
+        Ok(Self::from_paths(clock, StateStore::new()?, &paths))
+    }
+
+    /// Creates a daemon for the routines stored at the given paths, recording their state in
+    /// the given store
+    ///
+    /// Routines that cannot be read are left out, so that a broken file does not stop the others.
+    pub(crate) fn from_paths(
+        clock: C,
+        store: StateStore,
+        paths: &[PathBuf],
+    ) -> Self {
+        let now = clock.now();
+        let mut routines = Vec::new();
+        let mut broken = Vec::new();
+        for path in paths {
+            let loaded: Result<_, Box<dyn std::error::Error>> = Routine::read(path)
+                .map_err(|error| error.into())
+                .and_then(|routine| Ok((routine, store.load(path)?)));
+
+            match loaded {
+                Ok((routine, state)) => routines.push(Scheduled::new(path.clone(), routine, state, now)),
+                Err(error) => broken.push((path.clone(), error.to_string())),
+            }
+        }
+
+        Self { clock, store, routines, broken }
+    }
+
+    /// Throws every routine that is due, returning the outcome for each routine attempted
+    ///
+    /// Routines are thrown in parallel, except for routines syncing the same directory, which
+    /// are thrown one after the other so that they never race on its repository.
+    pub(crate) fn tick(&mut self) -> Vec<(String, Result<(), String>)> {
+        let now = self.clock.now();
+
+        let mut directories: BTreeMap<PathBuf, Vec<(usize, &mut Scheduled)>> = BTreeMap::new();
+        for (order, scheduled) in self.routines.iter_mut().enumerate() {
+            if scheduled.next_due() <= now {
+                // Components are compared so that "dir" and "dir/" are the same directory
+                let directory = Path::new(&scheduled.routine.base.path).components().collect();
+                directories.entry(directory).or_default().push((order, scheduled));
+            }
+        }
+
+        let store = &self.store;
+        let mut results = thread::scope(|scope| {
+            let threads = directories
+                .into_values()
+                .map(|group| {
+                    scope.spawn(move || {
+                        group
+                            .into_iter()
+                            .map(|(order, scheduled)| {
+                                let result = scheduled.sync_guarded(store, now);
+                                (order, scheduled.routine.base.title.clone(), result)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            // A thread that died anyway loses its results, not the daemon
+            threads
+                .into_iter()
+                .flat_map(|thread| {
+                    thread.join().unwrap_or_else(|panic| {
+                        eprintln!("[{}] A routine thread panicked: {}", now, panic_message(&*panic));
+                        Vec::new()
+                    })
+                })
+                .collect::<Vec<_>>()
+        });
+
+        // Report in the order routines are listed, whichever finished first
+        results.sort_by_key(|(order, _, _)| *order);
+        results.into_iter().map(|(_, title, result)| (title, result)).collect()
+    }
+
+    /// Returns how long to sleep until the next routine is due, at most `POLL_INTERVAL`
+    pub(crate) fn time_to_next(&self) -> Duration {
+        let now = self.clock.now();
+
+        self.routines
+            .iter()
+            .map(|scheduled| scheduled.next_due() - now)
+            .min()
+            .unwrap_or(POLL_INTERVAL)
+            .clamp(Duration::zero(), POLL_INTERVAL)
+    }
+
+    /// Throws the routines that are due, then sleeps until the next one is
+    pub(crate) fn step(&mut self) -> Vec<(String, Result<(), String>)> {
+        let results = self.tick();
+        self.clock.sleep(self.time_to_next());
+        results
+    }
+
+    /// Runs the daemon forever
+    pub(crate) fn run(&mut self) -> ! {
+        loop {
+            for (title, result) in self.step() {
+                match result {
+                    Ok(()) => println!("[{}] Threw {}", self.clock.now(), title),
+                    Err(error) => eprintln!("[{}] Error throwing {}: {}", self.clock.now(), title, error),
+                }
+            }
+        }
+    }
+}
+
+/// Returns the message a panic was raised with
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
@@ -0,0 +1,124 @@
+use git2::{Index, IndexEntry, IndexTime, Repository};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::Metadata;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::library::codec::git_mode;
+
+/// Repository config key remembering how the files in the index were encoded
+const CODEC_CONFIG_KEY: &str = "act.codec";
+
+/// How a routine finds the files to re-add when staging, as written in `sync_method`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncMethod {
+    /// Read, hash and re-add every file of the directory
+    #[default]
+    Thorough,
+    /// Only re-add files whose modification time, size, inode or mode changed since the last
+    /// stage, as recorded in the index
+    Delta,
+}
+
+impl fmt::Display for SyncMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SyncMethod::Thorough => "thorough",
+            SyncMethod::Delta => "delta",
+        })
+    }
+}
+
+/// Decides which files of the working directory need to be read again
+///
+/// The index doubles as the cache: every staged entry carries the stat data of the file it was
+/// read from, whether it was added by libgit2 or encoded by ACT-IV.
+pub(crate) struct DeltaCache {
+    enabled: bool,
+    index_time: Option<SystemTime>,
+}
+
+impl DeltaCache {
+    /// Prepares the index for staging with the given method
+    ///
+    /// The index is emptied for thorough syncs, and whenever the files in it were encoded
+    /// differently than they would be now, as their stat data then says nothing about the blobs.
+    pub(crate) fn prepare(
+        repo: &Repository,
+        index: &mut Index,
+        method: SyncMethod,
+        codec: &str,
+    ) -> Result<Self, git2::Error> {
+        let mut config = repo.config()?;
+        let same_codec = config.get_string(CODEC_CONFIG_KEY).is_ok_and(|previous| previous == codec);
+        let enabled = method == SyncMethod::Delta && same_codec;
+
+        if !enabled {
+            index.clear()?;
+        }
+        if !same_codec {
+            config.set_str(CODEC_CONFIG_KEY, codec)?;
+        }
+
+        let index_time = index
+            .path()
+            .and_then(|path| path.metadata().ok())
+            .and_then(|metadata| metadata.modified().ok());
+
+        Ok(DeltaCache { enabled, index_time })
+    }
+
+    /// Returns whether the file can be left as it is in the index, without reading it
+    pub(crate) fn is_unchanged(&self, index: &Index, relative_path: &Path, metadata: &Metadata) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let Some(entry) = index.get_path(relative_path, 0) else {
+            return false;
+        };
+        let Ok(modified) = metadata.modified() else {
+            return false;
+        };
+
+        // Like git, distrust files modified after the index was last written, as they may have
+        // changed again within the same timestamp
+        if self.index_time.is_none_or(|index_time| modified >= index_time) {
+            return false;
+        }
+
+        let mtime = index_time(modified);
+        entry.mtime.seconds() == mtime.seconds()
+            && (entry.mtime.nanoseconds() == 0 || entry.mtime.nanoseconds() == mtime.nanoseconds())
+            && entry.file_size == metadata.len() as u32
+            && entry.ino == inode(metadata)
+            && entry.mode == git_mode(metadata)
+    }
+}
+
+/// Records the stat data of the file an entry was read from, so that it can be trusted later
+pub(crate) fn set_stat(entry: &mut IndexEntry, metadata: &Metadata) {
+    let modified = metadata.modified().map(index_time).unwrap_or(IndexTime::new(0, 0));
+
+    entry.mtime = modified;
+    entry.ctime = modified;
+    entry.ino = inode(metadata);
+    entry.file_size = metadata.len() as u32;
+}
+
+fn index_time(time: SystemTime) -> IndexTime {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    IndexTime::new(since_epoch.as_secs() as i32, since_epoch.subsec_nanos())
+}
+
+#[cfg(unix)]
+fn inode(metadata: &Metadata) -> u32 {
+    use std::os::unix::fs::MetadataExt;
+
+    metadata.ino() as u32
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &Metadata) -> u32 {
+    0
+}
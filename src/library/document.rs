@@ -0,0 +1,257 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, Table, TableLike, Value};
+use crate::library::config::write_atomic;
+use crate::library::routine::Routine;
+use crate::library::secret::{masked, MASK};
+
+/// A routine file as written by hand, edited without losing its comments or formatting
+///
+/// Settings are addressed by dotted keys such as `git.remote`, with numbers indexing arrays,
+/// as in `notify.0.kind`.
+pub(crate) struct RoutineDocument {
+    path: PathBuf,
+    document: DocumentMut,
+}
+
+impl RoutineDocument {
+    /// Opens the routine file at the given path
+    pub(crate) fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        let document = content
+            .parse::<DocumentMut>()
+            .map_err(|error| format!("Invalid routine file {}: {}", path.display(), error))?;
+
+        Ok(RoutineDocument { path: path.to_path_buf(), document })
+    }
+
+    /// Returns a setting as it would be written in the routine file, or the whole file without
+    /// a key
+    ///
+    /// Strings are returned without quotes. Secrets are masked unless `reveal` is set.
+    pub(crate) fn get(&self, key: Option<&str>, reveal: bool) -> Result<String, Box<dyn Error>> {
+        let mut document = self.document.clone();
+        if !reveal {
+            let secrets = secret_paths(&document);
+            mask_secrets(document.as_item_mut(), &mut Vec::new(), secrets.as_deref());
+        }
+
+        let Some(key) = key else {
+            return Ok(document.to_string().trim().to_string());
+        };
+        let item = lookup(document.as_item(), key)
+            .ok_or_else(|| format!("{} is not set in the routine", key))?
+            .clone();
+
+        let text = match item {
+            Item::Value(Value::String(value)) => value.into_value(),
+            Item::Value(mut value) => {
+                value.decor_mut().clear();
+                value.to_string()
+            },
+            item => item.to_string(),
+        };
+
+        Ok(text.trim().to_string())
+    }
+
+    /// Sets a setting, keeping the comments around it and everything else in the file as is
+    ///
+    /// The value is read as TOML, so `true`, `5` or `["*.tmp"]` keep their types, and as a
+    /// plain string when it is not valid TOML, replaces a string or only fits the setting as a
+    /// string, such as a numeric password. Settings that the routine does not know or values of
+    /// the wrong type are refused, leaving the document unchanged.
+    pub(crate) fn set(&mut self, key: &str, value: &str) -> Result<(), Box<dyn Error>> {
+        let previous = self.document.clone();
+
+        let result = self.insert(key, value, false).and_then(|_| self.check(key));
+        if result.is_err() {
+            self.document = previous.clone();
+            if self.insert(key, value, true).and_then(|_| self.check(key)).is_ok() {
+                return Ok(());
+            }
+            self.document = previous;
+        }
+
+        result
+    }
+
+    /// Writes the document back to the routine file
+    pub(crate) fn save(&self) -> Result<(), Box<dyn Error>> {
+        write_atomic(&self.path, &self.document.to_string())
+    }
+
+    /// Sets the value at the key, creating the tables leading to it, as a string if `as_string`
+    fn insert(&mut self, key: &str, value: &str, as_string: bool) -> Result<(), Box<dyn Error>> {
+        let parts = key.split('.').collect::<Vec<_>>();
+        let (last, parents) = parts.split_last().expect("split returns at least one part");
+
+        let mut item = self.document.as_item_mut();
+        for part in parents {
+            if part.parse::<usize>().is_err() && item.get(*part).is_none() {
+                item.as_table_mut()
+                    .ok_or_else(|| format!("Cannot set {}, it is not in a table", key))?
+                    .insert(part, Item::Table(Table::new()));
+            }
+            item = match part.parse::<usize>() {
+                Ok(index) => item.get_mut(index),
+                Err(_) => item.get_mut(*part),
+            }
+            .ok_or_else(|| format!("Cannot set {}, {} is out of range", key, part))?;
+        }
+
+        let table: &mut dyn TableLike = item
+            .as_table_like_mut()
+            .ok_or_else(|| format!("Cannot set {}, it is not in a table", key))?;
+
+        match table.get_mut(last) {
+            Some(Item::Value(current)) => {
+                let mut new = parse_value(value, Some(current), as_string);
+                *new.decor_mut() = current.decor().clone();
+                realign_comment(&mut new, repr_len(current));
+                *current = new;
+            },
+            Some(_) => return Err(format!("Cannot set {}, it is a table", key).into()),
+            None => {
+                let mut new = parse_value(value, None, as_string);
+                new.decor_mut().clear();
+                table.insert(last, Item::Value(new));
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Makes sure the document is still a valid routine in which the key means something
+    fn check(&self, key: &str) -> Result<(), Box<dyn Error>> {
+        let routine: Routine = toml::from_str(&self.document.to_string())
+            .map_err(|error| format!("Invalid value for {}: {}", key, error))?;
+
+        // Settings the routine does not know about are dropped when it is serialized again
+        let known = toml::to_string(&routine)?.parse::<DocumentMut>()?;
+        lookup(known.as_item(), key).ok_or_else(|| format!("Unknown routine setting {}", key))?;
+
+        Ok(())
+    }
+}
+
+/// Finds the item at a dotted key
+fn lookup<'a>(item: &'a Item, key: &str) -> Option<&'a Item> {
+    key.split('.').try_fold(item, |item, part| match part.parse::<usize>() {
+        Ok(index) => item.get(index),
+        Err(_) => item.get(part),
+    })
+}
+
+/// Reads a value given on the command line
+fn parse_value(value: &str, current: Option<&Value>, as_string: bool) -> Value {
+    if as_string {
+        return Value::from(value);
+    }
+
+    match (value.parse::<Value>(), current) {
+        (Ok(parsed @ Value::String(_)), _) => parsed,
+        (_, Some(Value::String(_))) | (Err(_), _) => Value::from(value),
+        (Ok(parsed), _) => parsed,
+    }
+}
+
+/// Length of a value as written, without the whitespace and comments around it
+fn repr_len(value: &Value) -> usize {
+    let mut value = value.clone();
+    value.decor_mut().clear();
+    value.to_string().len()
+}
+
+/// Keeps the comment after a value in the same column when the value changes length
+fn realign_comment(value: &mut Value, previous_len: usize) {
+    let len = repr_len(value);
+    let Some(suffix) = value.decor().suffix().and_then(|suffix| suffix.as_str()) else {
+        return;
+    };
+    let comment = suffix.trim_start_matches(' ');
+    if !comment.starts_with('#') {
+        return;
+    }
+
+    let padding = (suffix.len() - comment.len() + previous_len).saturating_sub(len).max(1);
+    let suffix = format!("{}{}", " ".repeat(padding), comment);
+    value.decor_mut().set_suffix(suffix);
+}
+
+/// Returns the paths of the secrets of the routine, found by serializing it with and without
+/// its `Secret` values masked, or `None` when the document is not a valid routine
+fn secret_paths(document: &DocumentMut) -> Option<Vec<Vec<String>>> {
+    let routine: Routine = toml::from_str(&document.to_string()).ok()?;
+    let plain = toml::Value::try_from(&routine).ok()?;
+    let masked = masked(|| toml::Value::try_from(&routine)).ok()?;
+
+    let mut paths = Vec::new();
+    diff_paths(&plain, &masked, &mut Vec::new(), &mut paths);
+    Some(paths)
+}
+
+/// Collects the paths at which the two values differ
+fn diff_paths(plain: &toml::Value, masked: &toml::Value, path: &mut Vec<String>, paths: &mut Vec<Vec<String>>) {
+    match (plain, masked) {
+        (toml::Value::Table(plain), toml::Value::Table(masked)) => {
+            for (key, value) in plain {
+                if let Some(masked) = masked.get(key) {
+                    path.push(key.clone());
+                    diff_paths(value, masked, path, paths);
+                    path.pop();
+                }
+            }
+        },
+        (toml::Value::Array(plain), toml::Value::Array(masked)) => {
+            for (index, (value, masked)) in plain.iter().zip(masked).enumerate() {
+                path.push(index.to_string());
+                diff_paths(value, masked, path, paths);
+                path.pop();
+            }
+        },
+        (plain, masked) if plain != masked => paths.push(path.clone()),
+        _ => {},
+    }
+}
+
+/// Replaces every sensitive string under the item, found at the path, with a mask
+///
+/// Without the paths of the secrets, as for a routine that is not valid, every string is masked.
+fn mask_secrets(item: &mut Item, path: &mut Vec<String>, secrets: Option<&[Vec<String>]>) {
+    if let Some(table) = item.as_table_like_mut() {
+        mask_table(table, path, secrets);
+    } else if let Some(tables) = item.as_array_of_tables_mut() {
+        for (index, table) in tables.iter_mut().enumerate() {
+            path.push(index.to_string());
+            mask_table(table, path, secrets);
+            path.pop();
+        }
+    } else if let Some(array) = item.as_array_mut() {
+        for (index, value) in array.iter_mut().enumerate() {
+            if let Some(table) = value.as_inline_table_mut() {
+                path.push(index.to_string());
+                mask_table(table, path, secrets);
+                path.pop();
+            }
+        }
+    } else if let Some(value) = item
+        .as_value_mut()
+        .filter(|value| value.is_str() && secrets.is_none_or(|secrets| secrets.contains(path)))
+    {
+        let previous_len = repr_len(value);
+        let mut masked = Value::from(MASK);
+        *masked.decor_mut() = value.decor().clone();
+        realign_comment(&mut masked, previous_len);
+        *value = masked;
+    }
+}
+
+fn mask_table(table: &mut dyn TableLike, path: &mut Vec<String>, secrets: Option<&[Vec<String>]>) {
+    for (key, item) in table.iter_mut() {
+        path.push(key.get().to_string());
+        mask_secrets(item, path, secrets);
+        path.pop();
+    }
+}
@@ -0,0 +1,118 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use crate::library::crypt::CRYPT_HEADER_FILE_NAME;
+
+/// Name of the ignore file read from every directory of a synced directory, alongside `.gitignore`
+pub(crate) const ACTIGNORE_FILE_NAME: &str = ".actignore";
+
+/// Decides which files of a synced directory are left out of its snapshots
+///
+/// Patterns use gitignore semantics (negation with `!`, directory-only patterns ending in `/`,
+/// `**` wildcards) and are read from the `.gitignore` and `.actignore` files of the directory
+/// and of its subdirectories, then from the routine's `advanced.exclude`. As with git, the
+/// ignore files of a subdirectory take precedence over those of its parents, and the routine's
+/// patterns take precedence over every ignore file.
+pub(crate) struct Excluder {
+    root: PathBuf,
+    patterns: Gitignore,
+    ignore_files: Vec<Gitignore>, // One matcher per directory with ignore files, deepest first
+}
+
+impl Excluder {
+    /// Builds the exclusion rules for the directory at `root`
+    pub(crate) fn new(root: &Path, patterns: &[String]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in patterns {
+            builder.add_line(None, pattern)?;
+        }
+
+        let mut excluder = Self {
+            root: root.to_path_buf(),
+            patterns: builder.build()?,
+            ignore_files: Vec::new(),
+        };
+        if root.is_dir() {
+            excluder.add_ignore_files(Path::new(""))?;
+        }
+
+        Ok(excluder)
+    }
+
+    /// Reads the ignore files of the directory, relative to the root, then of its subdirectories
+    /// that are not excluded
+    fn add_ignore_files(&mut self, relative_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let dir = self.root.join(relative_dir);
+
+        let mut builder = GitignoreBuilder::new(&dir);
+        let mut found = false;
+        for file_name in [".gitignore", ACTIGNORE_FILE_NAME] {
+            let ignore_file = dir.join(file_name);
+            if ignore_file.is_file() {
+                if let Some(error) = builder.add(&ignore_file) {
+                    return Err(Box::new(error));
+                }
+                found = true;
+            }
+        }
+        if found {
+            // Directories are read parents first, so the deepest ignore files end up first
+            self.ignore_files.insert(0, builder.build()?);
+        }
+
+        let mut subdirs = fs::read_dir(&dir)?
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+            .map(|entry| relative_dir.join(entry.file_name()))
+            .collect::<Vec<_>>();
+        subdirs.sort();
+
+        for subdir in subdirs {
+            if !self.is_excluded(&subdir, true) {
+                self.add_ignore_files(&subdir)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether the path, relative to the synced directory, is excluded
+    ///
+    /// A path is also excluded when any of its parent directories is.
+    pub(crate) fn is_excluded(&self, relative_path: &Path, is_dir: bool) -> bool {
+        // The repository itself is never part of a snapshot, and the encryption header is
+        // committed separately
+        if relative_path.components().next() == Some(Component::Normal(".git".as_ref()))
+            || relative_path == Path::new(CRYPT_HEADER_FILE_NAME)
+        {
+            return true;
+        }
+
+        let mut path = self.root.clone();
+        let mut components = relative_path.components().peekable();
+        while let Some(component) = components.next() {
+            path.push(component);
+            let last = components.peek().is_none();
+            if self.is_ignored(&path, !last || is_dir) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Returns whether a single path is ignored, without looking at its parents, the first
+    /// patterns that match it deciding
+    fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let applying = self
+            .ignore_files
+            .iter()
+            .filter(|matcher| path.parent().is_some_and(|parent| parent.starts_with(matcher.path())));
+
+        std::iter::once(&self.patterns)
+            .chain(applying)
+            .map(|matcher| matcher.matched(path, is_dir))
+            .find(|matched| !matched.is_none())
+            .is_some_and(|matched| matched.is_ignore())
+    }
+}
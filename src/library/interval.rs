@@ -0,0 +1,119 @@
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// How often a routine is synced, as written in its `commit_interval`
+///
+/// Either a duration shorthand such as "30m", "6h", "1d" or "1w", measured from the last sync,
+/// or a cron expression such as "0 9 * * Mon-Fri" evaluated on the routine's wall clock.
+#[derive(Debug, Clone)]
+pub struct Interval {
+    expression: String,
+    kind: IntervalKind,
+}
+
+#[derive(Debug, Clone)]
+enum IntervalKind {
+    Every(Duration),
+    Cron(Box<cron::Schedule>),
+}
+
+/// Parses a duration shorthand such as "30s", "15m", "6h", "1d" or "1w"
+fn parse_duration(expression: &str) -> Result<Duration, String> {
+    let split = expression.find(|c: char| !c.is_ascii_digit()).unwrap_or(expression.len());
+    let (amount, unit) = expression.split_at(split);
+
+    let amount: i64 =
+        amount.parse().map_err(|_| format!("Invalid commit interval: {:?}", expression))?;
+    let duration = match unit {
+        "s" => Duration::seconds(amount),
+        "m" => Duration::minutes(amount),
+        "h" => Duration::hours(amount),
+        "d" => Duration::days(amount),
+        "w" => Duration::weeks(amount),
+        _ => return Err(format!("Invalid commit interval unit: {:?}", expression)),
+    };
+
+    if duration <= Duration::zero() {
+        return Err(format!("Commit interval must be positive: {:?}", expression));
+    }
+
+    Ok(duration)
+}
+
+/// Parses a cron expression, accepting the classic five fields without seconds
+fn parse_cron(expression: &str) -> Result<cron::Schedule, String> {
+    let normalized = if expression.split_whitespace().count() == 5 {
+        format!("0 {}", expression)
+    } else {
+        expression.to_string()
+    };
+
+    cron::Schedule::from_str(&normalized)
+        .map_err(|error| format!("Invalid cron expression {:?}: {}", expression, error))
+}
+
+impl Interval {
+    /// Returns the first run strictly after `after`, if there is any
+    ///
+    /// Cron expressions are evaluated in `time_zone`, so "0 9 * * *" runs at 09:00 local time
+    /// regardless of daylight saving changes.
+    pub fn next_after(&self, after: DateTime<Utc>, time_zone: &Tz) -> Option<DateTime<Utc>> {
+        match &self.kind {
+            IntervalKind::Every(duration) => Some(after + *duration),
+            IntervalKind::Cron(schedule) => schedule
+                .after(&after.with_timezone(time_zone))
+                .next()
+                .map(|time| time.with_timezone(&Utc)),
+        }
+    }
+}
+
+impl Default for Interval {
+    fn default() -> Self {
+        Interval { expression: String::from("1d"), kind: IntervalKind::Every(Duration::days(1)) }
+    }
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let expression = expression.trim();
+
+        // Anything with spaces or a cron alias such as "@daily" is a cron expression
+        let kind = if expression.starts_with('@') || expression.contains(char::is_whitespace) {
+            IntervalKind::Cron(Box::new(parse_cron(expression)?))
+        } else {
+            IntervalKind::Every(parse_duration(expression)?)
+        };
+
+        Ok(Interval { expression: expression.to_string(), kind })
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+impl PartialEq for Interval {
+    fn eq(&self, other: &Self) -> bool {
+        self.expression == other.expression
+    }
+}
+
+impl Serialize for Interval {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.expression)
+    }
+}
+
+impl<'de> Deserialize<'de> for Interval {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+    }
+}
@@ -1,26 +1,235 @@
-use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::fs::{OpenOptions, read_dir};
-use std::io::{Read, Write};
+use std::io::{ErrorKind, Read, Write};
 use std::path::{PathBuf, Path};
+use tabled::Tabled;
 use crate::library::config::Configuration;
 use crate::library::routine::Routine;
-use serde_yaml;
+use crate::library::state::StateStore;
 
+/// The registry of routines, mapping the name of each routine to its file
+///
+/// Names start out as the routine's title, made unique when several routines share it, and then
+/// stay the same whatever the title becomes, until renamed with `act rename`.
 pub(crate) struct Lister {
     pub(crate) file_path: PathBuf,
-    pub(crate) paths: HashMap<String, PathBuf>,
+    pub(crate) paths: HashMap<String, PathBuf>, // Routine files by name
+    pub(crate) changes: ListerChanges, // What changed since the lister file was last written
 }
 
 pub(crate) const LISTER_FILE_NAME: &str = "lister.yaml";  // Name of the file used to store the paths
 
+/// Differences between the routines recorded in the lister file and the routine files found
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct ListerChanges {
+    pub(crate) added: Vec<(String, PathBuf)>,
+    pub(crate) removed: Vec<(String, PathBuf)>,
+    pub(crate) moved: Vec<(String, PathBuf, PathBuf)>, // Name, previous path and new path
+    pub(crate) duplicates: Vec<(String, Vec<(String, PathBuf)>)>, // Shared title, with the name and path of each routine
+    pub(crate) broken: Vec<(String, PathBuf, String)>, // Name, path and error of the routines that cannot be read
+}
+
+impl ListerChanges {
+    /// Returns whether the lister file already lists every routine as it should
+    ///
+    /// Duplicated titles and broken routines are only warnings, as the lister file lists them too.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+
+    /// Returns whether some routines need attention, as they share a title or are broken
+    pub(crate) fn has_warnings(&self) -> bool {
+        !self.duplicates.is_empty() || !self.broken.is_empty()
+    }
+}
+
+impl fmt::Display for ListerChanges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, path) in &self.added {
+            writeln!(f, "+ {}: {}", name, path.display())?;
+        }
+        for (name, path) in &self.removed {
+            writeln!(f, "- {}: {}", name, path.display())?;
+        }
+        for (name, previous, path) in &self.moved {
+            writeln!(f, "~ {}: {} -> {}", name, previous.display(), path.display())?;
+        }
+        for (title, routines) in &self.duplicates {
+            let routines = routines
+                .iter()
+                .map(|(name, path)| format!("{} ({})", name, path.display()))
+                .collect::<Vec<_>>();
+            writeln!(f, "! {} is the title of several routines: {}", title, routines.join(", "))?;
+        }
+        for (name, _, error) in &self.broken {
+            // The error already names the routine file
+            writeln!(f, "! {} is broken: {}", name, error)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Routine files found in the configuration directory
+#[derive(Default)]
+struct Scanned {
+    routines: Vec<(String, PathBuf)>, // Title and path of each valid routine
+    broken: Vec<(PathBuf, String)>, // Path and error of each file that is not a valid routine
+}
+
+/// Health of a routine, as listed by `act list`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Status {
+    /// Synced and not due yet
+    Ok,
+    /// Due for a sync, or never synced
+    Due,
+    /// The last sync failed
+    Failing,
+    /// The routine file is not a valid routine
+    Broken,
+    /// The routine file or its state could not be read
+    Unreadable,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Ok => "ok",
+            Status::Due => "due",
+            Status::Failing => "failing",
+            Status::Broken => "broken",
+            Status::Unreadable => "unreadable",
+        })
+    }
+}
+
+#[derive(Tabled, Serialize, Debug)] // A registered routine along with its health, as listed by `act list`
+pub(crate) struct Summary {
+    pub(crate) name: String,
+    pub(crate) title: String, // Empty if the routine cannot be read
+    pub(crate) path: String, // Directory the routine syncs, empty if the routine cannot be read
+    pub(crate) file: String, // Routine file
+    pub(crate) remote: String,
+    pub(crate) interval: String,
+    #[tabled(rename = "last success", display_with("display_last_success", self))]
+    pub(crate) last_success: Option<DateTime<Utc>>,
+    #[tabled(rename = "next due", display_with("display_next_due", self))]
+    pub(crate) next_due: Option<DateTime<Utc>>, // `None` if the routine is never due again
+    #[tabled(display_with("display_status", self))]
+    pub(crate) status: Status,
+    #[tabled(skip)]
+    pub(crate) due: bool,
+    #[tabled(skip)]
+    pub(crate) consecutive_failures: u32,
+    #[tabled(skip)]
+    pub(crate) last_error: Option<String>,
+    #[tabled(skip)]
+    #[serde(skip)]
+    time_zone: Tz,
+}
+
+impl Summary {
+    /// Summarizes the routine stored at `routine_path`, as of `now`
+    ///
+    /// Overdue routines are due at `now`.
+    pub(crate) fn new(name: &str, routine_path: &Path, store: &StateStore, now: DateTime<Utc>) -> Self {
+        let (routine, state) = match Routine::read(&routine_path.to_path_buf())
+            .map_err(|error| (error.kind() == ErrorKind::InvalidData, error.to_string()))
+            .and_then(|routine| Ok((routine, store.load(routine_path).map_err(|error| (false, error.to_string()))?)))
+        {
+            Ok(loaded) => loaded,
+            Err((broken, error)) => {
+                return Summary {
+                    name: name.to_string(),
+                    title: String::new(),
+                    path: String::new(),
+                    file: routine_path.display().to_string(),
+                    remote: String::new(),
+                    interval: String::new(),
+                    last_success: None,
+                    next_due: None,
+                    status: if broken { Status::Broken } else { Status::Unreadable },
+                    due: false,
+                    consecutive_failures: 0,
+                    last_error: Some(error),
+                    time_zone: Tz::UTC,
+                }
+            },
+        };
+
+        let interval = &routine.base.interval;
+        let last_success = state.last_sync(interval);
+        let next_due = interval.next_due(last_success).map(|due| due.max(now));
+        let due = next_due.is_some_and(|due| due <= now);
+        let status = if state.consecutive_failures > 0 {
+            Status::Failing
+        } else if due {
+            Status::Due
+        } else {
+            Status::Ok
+        };
+
+        Summary {
+            name: name.to_string(),
+            title: routine.base.title.clone(),
+            path: routine.base.path.clone(),
+            file: routine_path.display().to_string(),
+            remote: routine.git.remote.clone(),
+            interval: interval.commit_interval.to_string(),
+            last_success,
+            next_due,
+            status,
+            due,
+            consecutive_failures: state.consecutive_failures,
+            last_error: state.last_error,
+            time_zone: interval.time_zone,
+        }
+    }
+
+    /// Returns whether the routine needs attention, as its last sync failed or it cannot be read
+    pub(crate) fn is_failing(&self) -> bool {
+        matches!(self.status, Status::Failing | Status::Broken | Status::Unreadable)
+    }
+
+    fn format_time(&self, time: DateTime<Utc>) -> String {
+        time.with_timezone(&self.time_zone).format("%Y-%m-%d %H:%M:%S %Z").to_string()
+    }
+}
+
+fn display_last_success(summary: &Summary) -> String {
+    summary.last_success.map_or("never".to_string(), |time| summary.format_time(time))
+}
+
+fn display_next_due(summary: &Summary) -> String {
+    match summary.next_due {
+        Some(_) if summary.due => "now".to_string(),
+        Some(time) => summary.format_time(time),
+        None => "never".to_string(),
+    }
+}
+
+fn display_status(summary: &Summary) -> String {
+    match summary.status {
+        Status::Failing => format!("failing ({})", summary.consecutive_failures),
+        status => status.to_string(),
+    }
+}
+
 impl Lister {
     /// Creates a new `Lister` instance by reading the configuration directory and initializing the paths
-    /// If the lister file is empty, it will initialize the paths by scanning the directory.
+    /// The paths recorded in the lister file are reconciled with the routine files of the directory.
     pub(crate) fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        // Retrieve the configuration directory
-        let config_dir = Configuration::get_dir()
-            .expect("Could not get config directory");
+        Self::at(&Configuration::get_dir()?)
+    }
 
+    /// Creates a `Lister` for the routines of the given configuration directory
+    pub(crate) fn at(config_dir: &Path) -> Result<Self, Box<dyn std::error::Error>> {
         // Construct the file path to the lister YAML file
         let file_path = config_dir.join(LISTER_FILE_NAME);
 
@@ -29,89 +238,179 @@ impl Lister {
             .read(true)
             .write(true)
             .create(true)
+            .truncate(false)
             .open(&file_path)?;
 
         // Read the contents of the file into a buffer
         let mut buffer = Vec::new();
         file.read_to_end(&mut buffer)?;
 
-        // If the file is empty, initialize paths from the configuration directory
-        let paths = if buffer.is_empty() {
-            Self::initialize_paths(&config_dir)?
-        } else {
-            // Otherwise, parse the YAML data from the file
-            Self::parse_lister_file(&buffer)?
-        };
-
-        Ok(Self { file_path, paths })
-    }
+        // Write a comment indicating the start of the file if it's being created
+        if buffer.is_empty() {
+            file.write_all(r"# Lister file start".as_bytes())?;
+        }
 
-    /// Initializes the paths by scanning the configuration directory for valid `.toml` files
-    /// It writes an initial comment to the file if it's being created.
-    fn initialize_paths(config_dir: &Path) -> Result<HashMap<String, PathBuf>, Box<dyn std::error::Error>> {
-        // Open the lister file for writing (will create it if it doesn't exist)
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .open(config_dir.join(LISTER_FILE_NAME))?;
+        let (paths, changes) = Self::parse_lister_file(config_dir, &buffer)?;
 
-        // Write a comment indicating the start of the file
-        file.write_all(r"# Lister file start".as_bytes())?;
+        Ok(Self { file_path, paths, changes })
+    }
 
+    /// Scans the configuration directory for `.toml` routine files, returning the titles and
+    /// canonicalized paths of the valid ones and the paths and errors of the broken ones, both
+    /// sorted by path
+    fn scan(config_dir: &Path) -> Result<Scanned, Box<dyn std::error::Error>> {
         // Scan the configuration directory for `.toml` files excluding the config file itself
-        let paths = read_dir(config_dir)?
+        let mut paths = read_dir(config_dir)?
             .filter_map(|entry| entry.ok())  // Filter out any invalid entries
-            .filter(|entry| {
-                let path = entry.path();
+            .map(|entry| entry.path())
+            .filter(|path| {
                 // Only include files that are not directories, aren't the config file, and have a `.toml` extension
                 path.is_file() &&
                     path.file_name().map(|name| name != super::config::CONFIG_FILE_NAME && name.to_string_lossy().ends_with(".toml")).unwrap_or(false)
             })
-            .map(|entry| {
-                let path = entry.path();
-                // Read the routine data from each valid `.toml` file
-                let routine_data = Routine::read(&path).ok()?;
-                // Return the routine title and canonicalized path
-                Some((routine_data.base.title, dunce::canonicalize(path).ok()?))
-            })
-            .filter_map(|opt| opt)  // Filter out any `None` values from the above map operation
-            .collect::<HashMap<_, _>>();  // Collect the results into a HashMap
+            .filter_map(|path| dunce::canonicalize(path).ok())
+            .collect::<Vec<_>>();
+        paths.sort();
 
-        Ok(paths)
+        // Read the routine data from each file, keeping the error of those that cannot be read
+        let mut scanned = Scanned::default();
+        for path in paths {
+            match Routine::read(&path) {
+                Ok(routine) => scanned.routines.push((routine.base.title, path)),
+                Err(error) => scanned.broken.push((path, error.to_string())),
+            }
+        }
+
+        Ok(scanned)
     }
 
     /// Parses the given buffer (which should be YAML data) into a HashMap of file paths
-    pub(crate) fn parse_lister_file(buffer: &[u8]) -> Result<HashMap<String, PathBuf>, Box<dyn std::error::Error>> {
-        // Convert the buffer to a string and parse the YAML content into a HashMap
+    /// The paths are reconciled with the routine files of the configuration directory, returning
+    /// what changed along with them.
+    pub(crate) fn parse_lister_file(
+        config_dir: &Path,
+        buffer: &[u8],
+    ) -> Result<(HashMap<String, PathBuf>, ListerChanges), Box<dyn std::error::Error>> {
+        let scanned = Self::scan(config_dir)?;
+        let (mut paths, mut changes) = Self::reconcile(&Self::read_recorded(buffer)?, scanned.routines);
+
+        // Broken routine files are registered too, under their file name, to show up in `act list`
+        for (path, _) in &scanned.broken {
+            if !paths.values().any(|listed| listed == path) {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                let name = unique_name(&stem, |name| paths.contains_key(name));
+                changes.added.push((name.clone(), path.clone()));
+                paths.insert(name, path.clone());
+            }
+        }
+        changes.added.sort();
+
+        // Report every listed routine that cannot be read, wherever its file is
+        for (name, path) in paths.iter().collect::<BTreeMap<_, _>>() {
+            let error = match scanned.broken.iter().find(|(broken, _)| broken == path) {
+                Some((_, error)) => error.clone(),
+                None if path.is_file() => match Routine::read(path) {
+                    Ok(_) => continue,
+                    Err(error) => error.to_string(),
+                },
+                None => continue,
+            };
+            changes.broken.push((name.clone(), path.clone(), error));
+        }
+
+        Ok((paths, changes))
+    }
+
+    /// Parses the paths recorded in the lister file, a file with only comments recording none
+    fn read_recorded(buffer: &[u8]) -> Result<HashMap<String, PathBuf>, Box<dyn std::error::Error>> {
         let data = String::from_utf8_lossy(buffer);
-        let mut paths: HashMap<String, PathBuf> = serde_yaml::from_str(&data)?;
+        let paths: Option<HashMap<String, PathBuf>> = serde_yaml::from_str(&data)?;
+
+        Ok(paths.unwrap_or_default())
+    }
 
-        // Check for updates
-        for path in read_dir(Configuration::get_dir().expect("Could not get config directory")).expect("Could not read config directory") {
-            let path = path?.path();
-            if path.is_dir() {
+    /// Works out the name of each routine, from the recorded paths and the routine files found
+    ///
+    /// Recorded routines keep their name as long as their file exists, even when it is broken or
+    /// outside the configuration directory, or when a file with their name as title replaces it.
+    /// New routine files are named after their title, suffixed with a number when the name is taken,
+    /// and titles shared by several routines are reported.
+    pub(crate) fn reconcile(
+        recorded: &HashMap<String, PathBuf>,
+        found: Vec<(String, PathBuf)>,
+    ) -> (HashMap<String, PathBuf>, ListerChanges) {
+        let mut changes = ListerChanges::default();
+        let mut titles = found.iter().map(|(title, path)| (path.clone(), title.clone())).collect::<HashMap<_, _>>();
+
+        // Keep the recorded routines whose file is still there, once each
+        let mut listed: BTreeMap<String, PathBuf> = BTreeMap::new();
+        for (name, path) in recorded.iter().collect::<BTreeMap<_, _>>() {
+            let current = dunce::canonicalize(path)
+                .ok()
+                .filter(|path| path.is_file() && !listed.values().any(|listed| listed == path));
+
+            match current {
+                Some(current) => {
+                    if !titles.contains_key(&current) {
+                        if let Ok(routine) = Routine::read(&current) {
+                            titles.insert(current.clone(), routine.base.title);
+                        }
+                    }
+                    if current != *path {
+                        changes.moved.push((name.clone(), path.clone(), current.clone()));
+                    }
+                    listed.insert(name.clone(), current);
+                },
+                None => changes.removed.push((name.clone(), path.clone())),
+            }
+        }
+
+        // Name the new routines after their title, unless they are a routine file that moved
+        for (title, path) in found {
+            if listed.values().any(|listed| *listed == path) {
                 continue;
             }
-            let file_name = String::from(path.file_name().expect("Could not get file name").to_string_lossy());
-            if !file_name.ends_with(".toml") {
+
+            if let Some(index) = changes.removed.iter().position(|(name, _)| *name == title) {
+                let (name, previous) = changes.removed.remove(index);
+                changes.moved.push((name.clone(), previous, path.clone()));
+                listed.insert(name, path);
                 continue;
             }
-            let routine = Routine::read(&path).expect(&format!("Could not read {}", file_name));
-            paths.insert(routine.base.title, dunce::canonicalize(&path).expect("Could not canonicalize path"));
+
+            let name = unique_name(&title, |name| listed.contains_key(name));
+            changes.added.push((name.clone(), path.clone()));
+            listed.insert(name, path);
+        }
+        changes.added.sort();
+        changes.moved.sort();
+
+        // Report the titles shared by several routines, which then need their name to be told apart
+        let mut by_title: BTreeMap<&str, Vec<(String, PathBuf)>> = BTreeMap::new();
+        for (name, path) in &listed {
+            if let Some(title) = titles.get(path) {
+                by_title.entry(title).or_default().push((name.clone(), path.clone()));
+            }
         }
+        changes.duplicates = by_title
+            .into_iter()
+            .filter(|(_, routines)| routines.len() > 1)
+            .map(|(title, routines)| (title.to_string(), routines))
+            .collect();
 
-        Ok(paths)
+        (listed.into_iter().collect(), changes)
     }
 
     /// Dumps the current paths into the lister YAML file
     pub(crate) fn dump(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        // Convert the paths HashMap to a YAML string
-        let output = serde_yaml::to_string(&self.paths)?;
+        // Convert the paths HashMap to a YAML string, sorted by name so that it diffs well
+        let output = serde_yaml::to_string(&self.paths.iter().collect::<BTreeMap<_, _>>())?;
 
         // Open the file for writing and save the YAML string
         let mut file = OpenOptions::new()
             .write(true)
             .create(true)
+            .truncate(true)
             .open(&self.file_path)
             .expect("Could not open file");
         file.write_all(output.as_bytes())
@@ -121,7 +420,13 @@ impl Lister {
     }
 
     /// Adds a new file path to the lister, using either a provided name or the title from the routine
-    pub(crate) fn add_file(&mut self, path_buf: &PathBuf, name: Option<String>) {
+    /// The name is suffixed with a number when it is already taken, and returned. Files that are
+    /// already listed keep their name.
+    pub(crate) fn add_file(&mut self, path_buf: &PathBuf, name: Option<String>) -> String {
+        if let Some((listed, _)) = self.paths.iter().find(|(_, path)| *path == path_buf) {
+            return listed.clone();
+        }
+
         // If a name is provided, use it as the key; otherwise, use the title from the routine file
         let title = name.unwrap_or_else(|| {
             Routine::read(path_buf)
@@ -129,8 +434,117 @@ impl Lister {
                 .unwrap_or_else(|_| String::from("Routine not found"))  // Default title if the routine is not found
         });
 
-        // Insert the file path into the HashMap under the determined title
-        self.paths.insert(title, path_buf.clone());
+        // Insert the file path into the HashMap under a name no other routine has
+        let name = unique_name(&title, |name| self.paths.contains_key(name));
+        self.paths.insert(name.clone(), path_buf.clone());
+        name
+    }
+
+    /// Gives the routine stored at `path` a new name
+    pub(crate) fn rename(&mut self, path: &Path, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if name.trim().is_empty() {
+            return Err("Routine names cannot be empty".into());
+        }
+        if self.paths.contains_key(name) {
+            return Err(format!("Another routine is already named {:?}", name).into());
+        }
+
+        let previous = self
+            .paths
+            .iter()
+            .find(|(_, listed)| listed.as_path() == path)
+            .map(|(previous, _)| previous.clone())
+            .ok_or_else(|| format!("{} is not registered, run `act sync` first", path.display()))?;
+
+        let path = self.paths.remove(&previous).expect("the name was just found");
+        self.paths.insert(name.to_string(), path);
+
+        Ok(())
+    }
+
+    /// Returns the paths of the routines a command acts on, sorted by name with `all`
+    pub(crate) fn select(&self, routine: Option<&str>, all: bool) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+        if !all {
+            let name = routine.ok_or("Name a routine or pass --all")?;
+            return Ok(vec![self.resolve(name)?]);
+        }
+
+        let mut routines = self.paths.iter().collect::<Vec<_>>();
+        if routines.is_empty() {
+            return Err("No routines are registered, run `act sync` to find them".into());
+        }
+
+        routines.sort();
+        Ok(routines.into_iter().map(|(_, path)| path.clone()).collect())
+    }
+
+    /// Returns the path of the routine with the given name or title, or of the routine file at
+    /// the given path, suggesting close names when there is none
+    pub(crate) fn resolve(&self, name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        if let Some(path) = self.paths.get(name) {
+            return Ok(path.clone());
+        }
+        if Path::new(name).is_file() {
+            return Ok(PathBuf::from(name));
+        }
+
+        // Routines renamed in the registry can still be found by their title, if it is unique
+        let mut titled = self
+            .paths
+            .iter()
+            .filter(|(_, path)| Routine::read(path).is_ok_and(|routine| routine.base.title == name))
+            .collect::<Vec<_>>();
+        titled.sort();
+        match titled.as_slice() {
+            [(_, path)] => return Ok(path.to_path_buf()),
+            [] => {},
+            several => {
+                let names = several.iter().map(|(name, _)| format!("{:?}", name)).collect::<Vec<_>>();
+                return Err(format!("Several routines are titled {:?}, name one of {}", name, names.join(", ")).into());
+            },
+        }
+
+        let suggestions = self.suggest(name);
+        if suggestions.is_empty() {
+            return Err(format!("No routine is named {:?} and no such file exists", name).into());
+        }
+
+        Err(format!("No routine is named {:?}, did you mean {}?", name, suggestions.join(" or ")).into())
+    }
+
+    /// Returns up to three routine names close to the given name, the closest first
+    fn suggest(&self, name: &str) -> Vec<String> {
+        let name = name.to_lowercase();
+        let mut scored = self
+            .paths
+            .keys()
+            .map(|candidate| {
+                let lowercase = candidate.to_lowercase();
+                let score = if lowercase == name || lowercase.starts_with(&name) {
+                    1.0
+                } else {
+                    strsim::jaro(&name, &lowercase)
+                };
+                (score, candidate)
+            })
+            // Same threshold as clap uses for its own suggestions
+            .filter(|(score, _)| *score > 0.7)
+            .collect::<Vec<_>>();
+
+        scored.sort_by(|(a, a_name), (b, b_name)| b.total_cmp(a).then(a_name.cmp(b_name)));
+        scored.into_iter().take(3).map(|(_, candidate)| format!("{:?}", candidate)).collect()
+    }
+
+    /// Summarizes every listed routine as of `now`, sorted by name
+    pub(crate) fn summaries(&self, store: &StateStore, now: DateTime<Utc>) -> Vec<Summary> {
+        let mut summaries = self
+            .paths
+            .iter()
+            .map(|(name, path)| Summary::new(name, path, store, now))
+            .collect::<Vec<_>>();
+
+        summaries.sort_by(|a, b| a.name.cmp(&b.name));
+        summaries
     }
 
     /// Returns a reference to the stored paths HashMap
@@ -138,3 +552,15 @@ impl Lister {
         &self.paths
     }
 }
+
+/// Returns the name, suffixed with the first number that makes it free when it is taken
+fn unique_name(name: &str, taken: impl Fn(&str) -> bool) -> String {
+    if !taken(name) {
+        return name.to_string();
+    }
+
+    (2..)
+        .map(|number| format!("{}-{}", name, number))
+        .find(|candidate| !taken(candidate))
+        .expect("some number is free")
+}
@@ -0,0 +1,366 @@
+use chrono::Utc;
+use git2::{Delta, Oid, Repository, Tree};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use wait_timeout::ChildExt;
+use crate::library::crypt::CRYPT_HEADER_FILE_NAME;
+use crate::library::routine::Ntfy;
+use crate::library::secret::Secret;
+
+/// Server notifications are published to when the routine does not name one
+pub(crate) const DEFAULT_NTFY_SERVER: &str = "https://ntfy.sh";
+
+/// How long sending a notification may take, so that a slow backend never holds a sync up
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Number of files a sync added, modified and deleted
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FileChanges {
+    pub added: usize,
+    pub modified: usize,
+    pub deleted: usize,
+}
+
+impl FileChanges {
+    /// Counts the files that differ between two trees, leaving out the encryption header
+    pub(crate) fn between(repo: &Repository, from: Option<&Tree<'_>>, to: &Tree<'_>) -> Result<Self, git2::Error> {
+        let diff = repo.diff_tree_to_tree(from, Some(to), None)?;
+        let mut changes = FileChanges::default();
+
+        for delta in diff.deltas() {
+            if delta.new_file().path() == Some(CRYPT_HEADER_FILE_NAME.as_ref()) {
+                continue;
+            }
+
+            match delta.status() {
+                Delta::Added | Delta::Copied => changes.added += 1,
+                Delta::Deleted => changes.deleted += 1,
+                _ => changes.modified += 1,
+            }
+        }
+
+        Ok(changes)
+    }
+}
+
+impl fmt::Display for FileChanges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} added, {} modified, {} deleted", self.added, self.modified, self.deleted)
+    }
+}
+
+/// What a successful sync did
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncReport {
+    /// Commit the routine is at after the sync
+    pub commit: Oid,
+    /// Files the sync changed, none if everything was already up to date
+    pub changes: FileChanges,
+}
+
+/// The outcome of a sync, as sent to notifiers
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    /// Title of the routine that was synced
+    pub routine: String,
+    /// What was done, "throw" or "catch"
+    pub action: String,
+    /// What the sync did, or why it failed
+    pub result: Result<SyncReport, String>,
+}
+
+impl Notification {
+    pub(crate) fn new(routine: &str, action: &str, result: Result<&SyncReport, &dyn Error>) -> Self {
+        Notification {
+            routine: routine.to_string(),
+            action: action.to_string(),
+            result: result.cloned().map_err(|error| error.to_string()),
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        self.result.is_ok()
+    }
+
+    /// Short summary, such as "Notes: throw succeeded"
+    pub fn title(&self) -> String {
+        let outcome = if self.success() { "succeeded" } else { "failed" };
+        format!("{}: {} {}", self.routine, self.action, outcome)
+    }
+
+    /// Details of what the sync did, or the error it failed with
+    pub fn message(&self) -> String {
+        match &self.result {
+            Ok(report) => format!("At commit {}: {}", &report.commit.to_string()[..8], report.changes),
+            Err(error) => error.clone(),
+        }
+    }
+
+    /// Every field of the notification, as posted to webhooks
+    ///
+    /// Fields that do not apply, such as the error of a successful sync, are null.
+    pub fn to_json(&self) -> Value {
+        let report = self.result.as_ref().ok();
+
+        json!({
+            "routine": self.routine,
+            "action": self.action,
+            "status": if self.success() { "success" } else { "failure" },
+            "title": self.title(),
+            "message": self.message(),
+            "commit": report.map(|report| report.commit.to_string()),
+            "added": report.map(|report| report.changes.added),
+            "modified": report.map(|report| report.changes.modified),
+            "deleted": report.map(|report| report.changes.deleted),
+            "error": self.result.as_ref().err(),
+        })
+    }
+}
+
+/// Somewhere the outcome of syncs can be sent to
+pub(crate) trait Notifier {
+    /// Returns whether the notification is meant for this notifier
+    fn wants(&self, notification: &Notification) -> bool;
+
+    /// Sends the notification
+    fn send(&self, notification: &Notification) -> Result<(), Box<dyn Error>>;
+}
+
+/// Body of a message published to an ntfy server
+#[derive(Serialize)]
+struct NtfyMessage<'a> {
+    topic: &'a str,
+    title: &'a str,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    priority: Option<u8>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    tags: &'a [String],
+}
+
+impl Notifier for Ntfy {
+    fn wants(&self, notification: &Notification) -> bool {
+        let wanted = if notification.success() {
+            self.notification_on_success
+        } else {
+            self.notification_on_failure
+        };
+
+        wanted && !self.ntfy_topic.is_empty()
+    }
+
+    fn send(&self, notification: &Notification) -> Result<(), Box<dyn Error>> {
+        if let Some(priority) = self.priority.filter(|priority| !(1..=5).contains(priority)) {
+            return Err(format!("Invalid ntfy priority {}, expected 1 to 5", priority).into());
+        }
+
+        let server = self.server.as_deref().unwrap_or(DEFAULT_NTFY_SERVER);
+        let headers = self
+            .token
+            .iter()
+            .map(|token| ("Authorization".to_string(), format!("Bearer {}", token.expose())));
+
+        // Publishing as JSON to the server root lets titles hold any character, unlike headers
+        post_json(
+            server.trim_end_matches('/'),
+            headers,
+            NtfyMessage {
+                topic: &self.ntfy_topic,
+                title: &notification.title(),
+                message: &notification.message(),
+                priority: self.priority,
+                tags: &self.tags,
+            },
+        )
+    }
+}
+
+/// A notifier of a routine, as written in a `[[notify]]` entry
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct NotifyTarget {
+    #[serde(flatten)]
+    pub backend: NotifyBackend,
+    #[serde(default = "default_true")]
+    pub on_success: bool, // Send successful syncs to this notifier
+    #[serde(default = "default_true")]
+    pub on_failure: bool, // Send failed syncs to this notifier
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Where a `[[notify]]` entry sends notifications, selected by its `kind`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum NotifyBackend {
+    /// Posts the notification as JSON to a URL, which often embeds a token so is kept secret
+    Webhook {
+        url: Secret,
+        #[serde(default)]
+        headers: BTreeMap<String, Secret>,
+    },
+    /// Shows a desktop notification through `notify-send`
+    Desktop,
+    /// Runs a command without a shell, with the notification in `ACT_*` environment variables
+    Command { command: String },
+    /// Appends the notification to a file, as a line of text or as an mbox message
+    Log {
+        path: PathBuf,
+        #[serde(default)]
+        format: LogFormat,
+    },
+}
+
+/// How the log backend writes notifications
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// One line per notification
+    #[default]
+    Text,
+    /// One email per notification, readable by mail clients
+    Mbox,
+}
+
+impl NotifyTarget {
+    /// Describes the notifier for `act info`, with secrets masked unless `reveal` is set
+    pub fn describe(&self, reveal: bool) -> String {
+        let backend = match &self.backend {
+            NotifyBackend::Webhook { url, .. } => format!("webhook {}", url.display(reveal)),
+            NotifyBackend::Desktop => "desktop".to_string(),
+            NotifyBackend::Command { command } => format!("command {}", command),
+            NotifyBackend::Log { path, format: LogFormat::Text } => format!("log {}", path.display()),
+            NotifyBackend::Log { path, format: LogFormat::Mbox } => format!("mbox {}", path.display()),
+        };
+        let events = match (self.on_success, self.on_failure) {
+            (true, true) => "success and failure",
+            (true, false) => "success",
+            (false, true) => "failure",
+            (false, false) => "nothing",
+        };
+
+        format!("{} on {}", backend, events)
+    }
+}
+
+impl Notifier for NotifyTarget {
+    fn wants(&self, notification: &Notification) -> bool {
+        if notification.success() {
+            self.on_success
+        } else {
+            self.on_failure
+        }
+    }
+
+    fn send(&self, notification: &Notification) -> Result<(), Box<dyn Error>> {
+        match &self.backend {
+            NotifyBackend::Webhook { url, headers } => {
+                let headers =
+                    headers.iter().map(|(name, value)| (name.clone(), value.expose().to_string()));
+                post_json(url.expose(), headers, notification.to_json())
+            },
+            NotifyBackend::Desktop => {
+                let urgency = if notification.success() { "normal" } else { "critical" };
+                run_command(
+                    Command::new("notify-send")
+                        .arg("--app-name=ACT-IV")
+                        .arg(format!("--urgency={}", urgency))
+                        .arg(notification.title())
+                        .arg(notification.message()),
+                )
+            },
+            NotifyBackend::Command { command } => {
+                let words = shell_words::split(command)
+                    .map_err(|error| format!("Invalid notify command {:?}: {}", command, error))?;
+                let (program, arguments) =
+                    words.split_first().ok_or("The notify command is empty")?;
+
+                let mut process = Command::new(program);
+                process.args(arguments);
+                if let Value::Object(fields) = notification.to_json() {
+                    for (name, value) in fields {
+                        let value = match value {
+                            Value::Null => String::new(),
+                            Value::String(value) => value,
+                            value => value.to_string(),
+                        };
+                        process.env(format!("ACT_{}", name.to_uppercase()), value);
+                    }
+                }
+                run_command(&mut process)
+            },
+            NotifyBackend::Log { path, format } => {
+                let now = Utc::now();
+                let entry = match format {
+                    LogFormat::Text => format!(
+                        "{} {} {}: {}\n",
+                        now.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                        if notification.success() { "SUCCESS" } else { "FAILURE" },
+                        notification.title(),
+                        notification.message().replace('\n', " "),
+                    ),
+                    LogFormat::Mbox => format!(
+                        "From act-iv@localhost {}\nFrom: ACT-IV <act-iv@localhost>\nDate: {}\nSubject: {}\n\n{}\n\n",
+                        now.format("%a %b %e %H:%M:%S %Y"),
+                        now.to_rfc2822(),
+                        notification.title(),
+                        // Quote body lines that would otherwise start a new message
+                        notification.message().replace("\nFrom ", "\n>From "),
+                    ),
+                };
+
+                let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+                file.write_all(entry.as_bytes())?;
+
+                Ok(())
+            },
+        }
+    }
+}
+
+/// Posts a JSON body along with the given headers
+fn post_json(
+    url: &str,
+    headers: impl Iterator<Item = (String, String)>,
+    body: impl Serialize,
+) -> Result<(), Box<dyn Error>> {
+    let agent: ureq::Agent =
+        ureq::Agent::config_builder().timeout_global(Some(NOTIFY_TIMEOUT)).build().into();
+
+    let mut request = agent.post(url);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    request.send_json(body)?;
+
+    Ok(())
+}
+
+/// Runs a notification command, giving up on it after a while
+fn run_command(command: &mut Command) -> Result<(), Box<dyn Error>> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::null())
+        .spawn()
+        .map_err(|error| format!("Could not run {}: {}", program, error))?;
+
+    let Some(status) = child.wait_timeout(NOTIFY_TIMEOUT)? else {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(format!("{} timed out", program).into());
+    };
+    if !status.success() {
+        return Err(format!("{} failed with {}", program, status).into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,214 @@
+use bzip2::read::{BzDecoder, BzEncoder};
+use flate2::read::{GzDecoder, GzEncoder};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::error::Error;
+use std::fmt;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::thread;
+use liblzma::read::{XzDecoder, XzEncoder};
+use liblzma::stream::{Filters, LzmaOptions, Stream};
+
+/// Files smaller than this are committed uncompressed unless the routine says otherwise
+pub(crate) const DEFAULT_THRESHOLD: u64 = 1024 * 1024;
+
+/// Prefix of every compressed blob, followed by the method byte and the compressed data
+const MAGIC: &[u8] = b"ACT-IV\x02";
+
+/// Preset used by the xz and lzma2 methods, raw LZMA2 streams need it to be decoded again
+const LZMA_PRESET: u32 = 6;
+
+/// How files are compressed before being committed, as written in `[press].compression`
+///
+/// Anything that is not a known method is a command reading the file on its standard input and
+/// writing the compressed data on its standard output. It is run without a shell.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Compression {
+    #[default]
+    Nil,
+    Gz,
+    Xz,
+    Bz2,
+    Lzma2,
+    Command(String),
+}
+
+impl Compression {
+    /// Byte identifying the method in compressed blobs
+    fn method(&self) -> u8 {
+        match self {
+            Compression::Nil => 0,
+            Compression::Gz => 1,
+            Compression::Xz => 2,
+            Compression::Bz2 => 3,
+            Compression::Lzma2 => 4,
+            Compression::Command(_) => 5,
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value.trim() {
+            "" => return Err("Compression cannot be empty, use \"nil\" to disable it".to_string()),
+            "nil" => Compression::Nil,
+            "gz" => Compression::Gz,
+            "xz" => Compression::Xz,
+            "bz2" => Compression::Bz2,
+            "lzma2" => Compression::Lzma2,
+            command => Compression::Command(command.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Compression::Nil => "nil",
+            Compression::Gz => "gz",
+            Compression::Xz => "xz",
+            Compression::Bz2 => "bz2",
+            Compression::Lzma2 => "lzma2",
+            Compression::Command(command) => command,
+        })
+    }
+}
+
+impl Serialize for Compression {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Compression {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Turns the content of a file into its committed form
+///
+/// Files of at least `threshold` bytes are compressed, and stored as they are when compressing
+/// does not make them smaller. The output is framed whenever it is not the content itself, and
+/// so is content that would otherwise be mistaken for a frame.
+pub(crate) fn pack(content: &[u8], compression: &Compression, threshold: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+    if *compression != Compression::Nil && content.len() as u64 >= threshold {
+        let compressed = compress(content, compression)?;
+        if compressed.len() < content.len() {
+            return Ok(frame(compression, &compressed));
+        }
+    }
+
+    if content.starts_with(MAGIC) {
+        return Ok(frame(&Compression::Nil, content));
+    }
+
+    Ok(content.to_vec())
+}
+
+/// Turns the committed form of a file back into its content
+///
+/// The method is read from the data itself, so files compressed on another device are read
+/// whatever the local configuration is. Only custom commands need `decompression` to be set.
+pub(crate) fn unpack(data: &[u8], decompression: Option<&str>) -> Result<Vec<u8>, Box<dyn Error>> {
+    let Some(framed) = data.strip_prefix(MAGIC) else {
+        return Ok(data.to_vec());
+    };
+    let (&method, payload) = framed.split_first().ok_or("Compressed data is truncated")?;
+
+    let mut decoder: Box<dyn Read + '_> = match method {
+        0 => Box::new(payload),
+        1 => Box::new(GzDecoder::new(payload)),
+        2 => Box::new(XzDecoder::new(payload)),
+        3 => Box::new(BzDecoder::new(payload)),
+        4 => Box::new(XzDecoder::new_stream(payload, Stream::new_raw_decoder(&lzma2_filters()?)?)),
+        5 => {
+            let command = decompression.ok_or(
+                "This file was compressed by a command, set [press].decompression to read it",
+            )?;
+            return run_filter(command, payload);
+        },
+        _ => return Err(format!("Unknown compression method {}", method).into()),
+    };
+
+    let mut content = Vec::new();
+    decoder.read_to_end(&mut content)?;
+    Ok(content)
+}
+
+/// Compresses the data with the given method, without framing it
+fn compress(content: &[u8], compression: &Compression) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut encoder: Box<dyn Read + '_> = match compression {
+        Compression::Nil => Box::new(content),
+        Compression::Gz => Box::new(GzEncoder::new(content, flate2::Compression::default())),
+        Compression::Xz => Box::new(XzEncoder::new(content, LZMA_PRESET)),
+        Compression::Bz2 => Box::new(BzEncoder::new(content, bzip2::Compression::default())),
+        Compression::Lzma2 => {
+            Box::new(XzEncoder::new_stream(content, Stream::new_raw_encoder(&lzma2_filters()?)?))
+        },
+        Compression::Command(command) => return run_filter(command, content),
+    };
+
+    let mut compressed = Vec::new();
+    encoder.read_to_end(&mut compressed)?;
+    Ok(compressed)
+}
+
+/// Prefixes the payload with the magic and the method byte
+fn frame(compression: &Compression, payload: &[u8]) -> Vec<u8> {
+    [MAGIC, &[compression.method()], payload].concat()
+}
+
+/// Filters of the raw LZMA2 streams, the same on both ends as they are not stored
+fn lzma2_filters() -> Result<Filters, liblzma::stream::Error> {
+    let mut filters = Filters::new();
+    filters.lzma2(&LzmaOptions::new_preset(LZMA_PRESET)?);
+    Ok(filters)
+}
+
+/// Pipes the data through a command and returns what it printed
+fn run_filter(command: &str, input: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let words = shell_words::split(command)
+        .map_err(|error| format!("Invalid compression command {:?}: {}", command, error))?;
+    let (program, arguments) = words.split_first().ok_or("The compression command is empty")?;
+
+    let mut child = Command::new(program)
+        .args(arguments)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("Could not run {:?}: {}", command, error))?;
+
+    // Feed the input and drain stderr while reading the output, or large files would deadlock
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let (output, errors) = thread::scope(|scope| {
+        scope.spawn(move || stdin.write_all(input));
+        let errors = scope.spawn(move || {
+            let mut errors = Vec::new();
+            stderr.read_to_end(&mut errors).map(|_| errors)
+        });
+
+        let mut output = Vec::new();
+        let read = stdout.read_to_end(&mut output);
+        (read.map(|_| output), errors.join().ok().and_then(Result::ok).unwrap_or_default())
+    });
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(format!(
+            "{:?} failed with {}: {}",
+            command,
+            status,
+            String::from_utf8_lossy(&errors).trim()
+        )
+        .into());
+    }
+
+    Ok(output?)
+}
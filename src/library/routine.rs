@@ -1,12 +1,28 @@
-use chrono::Local;
-use git2::{IndexAddOption, Repository, Signature, Tree};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use gethostname::gethostname;
+use git2::{
+    Commit, Config, Cred, CredentialType, FetchOptions, Oid,
+    PushOptions, RemoteCallbacks, Repository, RepositoryInitOptions, Signature, Sort,
+    Tree,
+};
 use serde::{Deserialize, Serialize};
-use std::{fs, io::Error, path::PathBuf};
-use std::fs::File;
+use std::{collections::{HashMap, HashSet}, fs, io::{Error, ErrorKind}, path::{Path, PathBuf}};
 use tabled::{settings::*, Table, Tabled};
-use tempfile::tempdir;
-use toml;
 use walkdir::WalkDir;
+use zeroize::Zeroizing;
+use crate::library::codec::{add_blob, add_encoded, restore_tree, Codec};
+use crate::library::config::Configuration;
+use crate::library::crypt::CRYPT_HEADER_FILE_NAME;
+use crate::library::delta::{DeltaCache, SyncMethod};
+use crate::library::exclude::Excluder;
+use crate::library::interval::Interval;
+use crate::library::notify::{
+    FileChanges, Notification, Notifier, NotifyTarget, SyncReport, DEFAULT_NTFY_SERVER,
+};
+use crate::library::press::{Compression, DEFAULT_THRESHOLD};
+use crate::library::secret::{eval_password, Secret, DEFAULT_EVAL_TIMEOUT};
+use crate::library::state::RoutineState;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Routine {
@@ -15,15 +31,38 @@ pub struct Routine {
     pub press: Compress,
     pub advanced: Advanced,
     pub ntfy: Ntfy,
-    pub git: Git
+    pub git: Git,
+    #[serde(default, rename = "notify", skip_serializing_if = "Vec::is_empty")]
+    pub notifiers: Vec<NotifyTarget>, // Other notifiers, each a [[notify]] entry
 }
 
+/// Format of the timestamps used as default commit messages
+pub(crate) const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
 #[derive(Tabled, Debug)] // This struct will be used for displaying the data in a table
-struct Data<'a> {
+pub struct Data<'a> {
     name: &'a str,
     value: String,
 }
 
+/// Result of staging a routine
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StageOutcome {
+    /// A new commit was created with the given id
+    Committed(Oid),
+    /// Nothing changed since the last commit, so none was created
+    Unchanged,
+}
+
+#[derive(Tabled, Debug)] // A single commit in a routine's history, newest first
+pub struct Snapshot {
+    #[tabled(rename = "ago")]
+    pub index: usize,
+    pub id: String,
+    pub time: String,
+    pub message: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Base {
     pub title: String,
@@ -34,22 +73,27 @@ pub struct Base {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct BaseInterval {
     pub timestamp: bool,
-    pub commit_interval: String, // Example: "1d" for daily commit
-    pub sync_method: String,     // Example: "thorough" or "delta"
-    pub time_zone: String,
-    pub last_sync_time: Option<String>, // Optional timestamp of the last sync
+    pub commit_interval: Interval, // Example: "1d" for daily commit, or a cron expression
+    pub sync_method: SyncMethod, // Example: "thorough" or "delta"
+    pub time_zone: Tz,
+    pub last_sync_time: Option<String>, // Optional timestamp of the last sync, superseded by the state store once synced
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Encrypt {
     pub encrypt: bool,
-    pub password: Option<String>,      // Optional plain text password
+    pub password: Option<Secret>,      // Optional plain text password
     pub password_eval: Option<String>, // Command evaluation for more secure password
+    #[serde(default)]
+    pub password_eval_shell: bool, // Whether to run password_eval through the system shell
+    pub password_eval_timeout: Option<u64>, // Seconds password_eval may run, 30 by default
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Compress {
-    pub compression: String, // Compression method, e.g., "gz", "xz", etc.
+    pub compression: Compression, // Compression method, e.g., "gz", "xz", etc., or a command
+    pub threshold: Option<u64>, // Files smaller than this many bytes are not compressed, 1 MiB by default
+    pub decompression: Option<String>, // Command undoing a custom compression command
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -63,6 +107,11 @@ pub struct Ntfy {
     pub ntfy_topic: String,            // Notification topic
     pub notification_on_success: bool, // Notify on successful sync
     pub notification_on_failure: bool, // Notify on failed sync
+    pub server: Option<String>,        // ntfy server URL, https://ntfy.sh by default
+    pub priority: Option<u8>,          // Notification priority, from 1 (min) to 5 (max)
+    #[serde(default)]
+    pub tags: Vec<String>,             // Tags or emoji shortcodes shown with notifications
+    pub token: Option<Secret>,         // Access token for protected topics
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -70,19 +119,86 @@ pub struct Git {
     pub remote: String,   // Git remote URL
     pub branch: String,   // Git branch name
     pub force_push: bool, // Whether to force push the changes
+    pub author_name: Option<String>,  // Optional name to sign commits with
+    pub author_email: Option<String>, // Optional email to sign commits with
+    #[serde(default)]
+    pub append_hostname: bool, // Whether to append the hostname to the author name
+}
+
+impl Encrypt {
+    /// Returns the password used to encrypt and decrypt the routine's files
+    ///
+    /// `password_eval` takes precedence over the plain text `password`, which is only used when
+    /// no command is set. A failing command is an error rather than a fallback.
+    pub fn password(&self) -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+        if let Some(command) = &self.password_eval {
+            let timeout = self
+                .password_eval_timeout
+                .map_or(DEFAULT_EVAL_TIMEOUT, std::time::Duration::from_secs);
+            return eval_password(command, self.password_eval_shell, timeout);
+        }
+
+        self.password
+            .as_ref()
+            .map(|password| Zeroizing::new(password.expose().to_string()))
+            .ok_or_else(|| "No password is set in the [crypt] section".into())
+    }
 }
 
-/// Walks through the directory and returns a list of paths, excluding `.git` directories
-pub(crate) fn walk_directory(path: &str) -> Vec<PathBuf> {
+impl BaseInterval {
+    /// Returns the time of the last sync written in the routine file, if there is one
+    pub fn last_sync(&self) -> Option<DateTime<Utc>> {
+        self.last_sync_time
+            .as_deref()
+            .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+            .map(|time| time.with_timezone(&Utc))
+    }
+
+    /// Returns when the routine is next due after its last sync, `None` if it never will be
+    ///
+    /// Routines that were never synced are due immediately.
+    pub fn next_due(&self, last_sync: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+        match last_sync {
+            Some(last_sync) => self.commit_interval.next_after(last_sync, &self.time_zone),
+            None => Some(DateTime::<Utc>::MIN_UTC),
+        }
+    }
+
+    /// Returns the next `count` run times from `now`, overdue runs happening right away
+    pub fn upcoming_runs(
+        &self,
+        last_sync: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+        count: usize,
+    ) -> Vec<DateTime<Utc>> {
+        let mut runs = Vec::with_capacity(count);
+        let mut next = self.next_due(last_sync).map(|due| due.max(now));
+
+        while let Some(run) = next.filter(|_| runs.len() < count) {
+            runs.push(run);
+            next = self.commit_interval.next_after(run, &self.time_zone);
+        }
+
+        runs
+    }
+}
+
+/// Walks through the directory and returns the paths of its files, skipping excluded files and
+/// directories as well as the `.git` directory
+pub(crate) fn walk_directory(path: &str, excluder: &Excluder) -> Vec<PathBuf> {
     WalkDir::new(path)
+        .min_depth(1) // Skip the base directory itself
         .into_iter()
-        .filter_map(Result::ok) // Filter out errors
-        .filter(|entry| {
-            let entry_path = entry.path();
-            // Skip the .git directory and the base directory itself
-            !entry_path.starts_with(&format!("{}.git", path))
-                && entry_path != std::path::Path::new(path)
+        .filter_entry(|entry| {
+            // Pruning excluded directories here avoids walking their contents at all
+            entry
+                .path()
+                .strip_prefix(path)
+                .map(|relative| !excluder.is_excluded(relative, entry.file_type().is_dir()))
+                .unwrap_or(false)
         })
+        .filter_map(Result::ok) // Filter out errors
+        .filter(|entry| !entry.file_type().is_dir()) // Git only tracks files
         .map(|entry| entry.into_path())
         .collect()
 }
@@ -94,47 +210,186 @@ fn init_repo(repo: &Repository, publisher: &Signature, tree: &Tree<'_>) -> Resul
         publisher,    // Author and committer
         publisher,
         "ACT-IV Init", // Commit message
-        &tree,         // The tree (file changes)
+        tree,          // The tree (file changes)
         &[],           // No parents (this is the first commit)
     )?;
     println!("Initialized repo: {}", oid);
     Ok(())
 }
 
+/// Builds the callbacks used to authenticate against a remote
+///
+/// SSH remotes are authenticated through the running SSH agent, HTTPS remotes through the
+/// configured git credential helper.
+pub(crate) fn remote_callbacks(git_config: &Config) -> RemoteCallbacks<'_> {
+    let mut callbacks = RemoteCallbacks::new();
+    let mut attempts = 0;
+
+    callbacks.credentials(move |url, username_from_url, allowed_types| {
+        // libgit2 keeps asking as long as we keep answering, so give up eventually
+        attempts += 1;
+        if attempts > 3 {
+            return Err(git2::Error::from_str("Authentication failed"));
+        }
+
+        if allowed_types.contains(CredentialType::SSH_KEY) {
+            Cred::ssh_key_from_agent(username_from_url.unwrap_or("git"))
+        } else if allowed_types.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            Cred::credential_helper(git_config, url, username_from_url)
+        } else {
+            Cred::default()
+        }
+    });
+
+    callbacks
+}
+
+/// Returns the time a snapshot was taken
+///
+/// Prefers the timestamp written as the default commit message by `Routine::stage`, falling
+/// back to the commit time for commits with custom messages.
+fn snapshot_time(commit: &Commit<'_>) -> NaiveDateTime {
+    commit
+        .message()
+        .and_then(|message| NaiveDateTime::parse_from_str(message.trim(), TIMESTAMP_FORMAT).ok())
+        .or_else(|| {
+            Local.timestamp_opt(commit.time().seconds(), 0).single().map(|time| time.naive_local())
+        })
+        .unwrap_or_default()
+}
+
+/// Parses a date given as `YYYY-MM-DD HH:MM:SS` or `YYYY-MM-DD` (start of the day)
+fn parse_date(date: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(date, TIMESTAMP_FORMAT).ok().or_else(|| {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").ok().and_then(|day| day.and_hms_opt(0, 0, 0))
+    })
+}
+
 impl Routine {
+    /// Creates a routine syncing `path` with default settings for every section
+    pub fn new(title: String, path: String) -> Self {
+        Routine {
+            base: Base {
+                title,
+                path,
+                interval: BaseInterval {
+                    timestamp: true,
+                    commit_interval: Interval::default(),
+                    sync_method: SyncMethod::Thorough,
+                    time_zone: Tz::UTC,
+                    last_sync_time: None,
+                },
+            },
+            crypt: Encrypt {
+                encrypt: false,
+                password: None,
+                password_eval: None,
+                password_eval_shell: false,
+                password_eval_timeout: None,
+            },
+            press: Compress { compression: Compression::Nil, threshold: None, decompression: None },
+            advanced: Advanced { exclude: Vec::new(), sync_on_startup: false },
+            ntfy: Ntfy {
+                ntfy_topic: String::new(),
+                notification_on_success: false,
+                notification_on_failure: false,
+                server: None,
+                priority: None,
+                tags: Vec::new(),
+                token: None,
+            },
+            git: Git {
+                remote: String::new(),
+                branch: String::from("main"),
+                force_push: false,
+                author_name: None,
+                author_email: None,
+                append_hostname: false,
+            },
+            notifiers: Vec::new(),
+        }
+    }
+
     /// Reads the routine configuration from a TOML file
     pub fn read(file_path: &PathBuf) -> Result<Self, Error> {
         // Read the file content as a string
-        let routine_str = fs::read_to_string(&file_path)?;
+        let routine_str = fs::read_to_string(file_path)?;
 
-        // Parse the TOML string into the `Routine` struct
-        let routine: Routine = toml::from_str(&routine_str).unwrap();
+        // Parse the TOML string into the `Routine` struct, a malformed file being an invalid data error
+        let routine: Routine = toml::from_str(&routine_str).map_err(|error| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Invalid routine file {}: {}", file_path.display(), error.to_string().trim_end()),
+            )
+        })?;
         Ok(routine)
     }
 
-    pub fn get_data(&self) -> Vec<Data> {
+    /// Returns the routine configuration and run state as table rows, with sensitive values
+    /// masked unless `reveal` is set
+    pub fn get_data(&self, state: &RoutineState, reveal: bool) -> Vec<Data<'_>> {
+        let format_time = |time: DateTime<Utc>| {
+            time.with_timezone(&self.base.interval.time_zone).format("%Y-%m-%d %H:%M:%S %Z").to_string()
+        };
+        let last_sync = state.last_sync(&self.base.interval);
+
         // Collect all the configuration data into a vector of Data structs
         vec![
             Data { name: "Title", value: self.base.title.clone() },
             Data { name: "Path", value: self.base.path.clone() },
             Data { name: "Interval Timestamp", value: self.base.interval.timestamp.to_string() },
-            Data { name: "Commit Interval", value: self.base.interval.commit_interval.clone() },
-            Data { name: "Sync Method", value: self.base.interval.sync_method.clone() },
-            Data { name: "Time Zone", value: self.base.interval.time_zone.clone() },
+            Data { name: "Commit Interval", value: self.base.interval.commit_interval.to_string() },
+            Data { name: "Sync Method", value: self.base.interval.sync_method.to_string() },
+            Data { name: "Time Zone", value: self.base.interval.time_zone.to_string() },
+            Data { name: "Last Sync Time", value: last_sync.map_or("None".to_string(), format_time) },
             Data {
-                name: "Last Sync Time",
-                value: self.base.interval.last_sync_time.clone().unwrap_or("None".to_string()),
+                name: "Last Attempt",
+                value: state.last_attempt.map_or("None".to_string(), format_time),
+            },
+            Data { name: "Last Error", value: state.last_error.clone().unwrap_or("None".to_string()) },
+            Data { name: "Last Commit", value: state.last_commit.clone().unwrap_or("None".to_string()) },
+            Data { name: "Consecutive Failures", value: state.consecutive_failures.to_string() },
+            Data {
+                name: "Next Runs",
+                value: self
+                    .base
+                    .interval
+                    .upcoming_runs(last_sync, Utc::now(), 3)
+                    .into_iter()
+                    .map(format_time)
+                    .collect::<Vec<_>>()
+                    .join("\n"),
             },
             Data { name: "Encrypt", value: self.crypt.encrypt.to_string() },
             Data {
                 name: "Password",
-                value: self.crypt.password.clone().unwrap_or("None".to_string()),
+                value: self
+                    .crypt
+                    .password
+                    .as_ref()
+                    .map_or("None".to_string(), |password| password.display(reveal)),
             },
             Data {
                 name: "Password Eval",
                 value: self.crypt.password_eval.clone().unwrap_or("None".to_string()),
             },
-            Data { name: "Compression", value: self.press.compression.clone() },
+            Data { name: "Password Eval Shell", value: self.crypt.password_eval_shell.to_string() },
+            Data {
+                name: "Password Eval Timeout",
+                value: format!(
+                    "{}s",
+                    self.crypt.password_eval_timeout.unwrap_or(DEFAULT_EVAL_TIMEOUT.as_secs())
+                ),
+            },
+            Data { name: "Compression", value: self.press.compression.to_string() },
+            Data {
+                name: "Compression Threshold",
+                value: format!("{} bytes", self.press.threshold.unwrap_or(DEFAULT_THRESHOLD)),
+            },
+            Data {
+                name: "Decompression",
+                value: self.press.decompression.clone().unwrap_or("None".to_string()),
+            },
             Data { name: "Exclude", value: format!("{:?}", self.advanced.exclude) },
             Data { name: "Sync On Startup", value: self.advanced.sync_on_startup.to_string() },
             Data { name: "Notify Topic", value: self.ntfy.ntfy_topic.clone() },
@@ -146,17 +401,86 @@ impl Routine {
                 name: "Notification on Failure",
                 value: self.ntfy.notification_on_failure.to_string(),
             },
+            Data {
+                name: "Notify Server",
+                value: self.ntfy.server.clone().unwrap_or(DEFAULT_NTFY_SERVER.to_string()),
+            },
+            Data {
+                name: "Notify Priority",
+                value: self.ntfy.priority.map_or("None".to_string(), |priority| priority.to_string()),
+            },
+            Data { name: "Notify Tags", value: format!("{:?}", self.ntfy.tags) },
+            Data {
+                name: "Notify Token",
+                value: self.ntfy.token.as_ref().map_or("None".to_string(), |token| token.display(reveal)),
+            },
             Data { name: "Git Remote", value: self.git.remote.clone() },
             Data { name: "Git Branch", value: self.git.branch.clone() },
             Data { name: "Force Push", value: self.git.force_push.to_string() },
+            Data {
+                name: "Author Name",
+                value: self.git.author_name.clone().unwrap_or("None".to_string()),
+            },
+            Data {
+                name: "Author Email",
+                value: self.git.author_email.clone().unwrap_or("None".to_string()),
+            },
+            Data { name: "Append Hostname", value: self.git.append_hostname.to_string() },
+            Data {
+                name: "Notifiers",
+                value: self
+                    .notifiers
+                    .iter()
+                    .map(|notifier| notifier.describe(reveal))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            },
         ]
     }
 
+    /// Returns the identity used to sign the routine's commits
+    ///
+    /// The name and email are each taken from the routine's `[git]` section, then from the
+    /// global `config.toml`, then from the user's git config.
+    pub fn signature(&self, repo: &Repository) -> Result<Signature<'static>, git2::Error> {
+        self.signature_with(repo, &Configuration::read().unwrap_or_default())
+    }
+
+    /// Returns the identity used to sign the routine's commits, given the global configuration
+    pub(crate) fn signature_with(
+        &self,
+        repo: &Repository,
+        global_config: &HashMap<String, String>,
+    ) -> Result<Signature<'static>, git2::Error> {
+        let git_config = repo.config().ok();
+
+        let resolve = |routine_value: &Option<String>, config_key: &str, git_key: &str| {
+            routine_value
+                .clone()
+                .or_else(|| global_config.get(config_key).cloned())
+                .or_else(|| git_config.as_ref()?.get_string(git_key).ok())
+        };
+
+        let mut name = resolve(&self.git.author_name, "author_name", "user.name")
+            .unwrap_or_else(|| String::from("ACT-IV"));
+        let email = resolve(&self.git.author_email, "author_email", "user.email")
+            .unwrap_or_else(|| String::from("act-iv@localhost"));
+
+        // Tell apart the snapshots of each device syncing the same routine
+        if self.git.append_hostname {
+            name = format!("{} ({})", name, gethostname().to_string_lossy());
+        }
+
+        Signature::now(&name, &email)
+    }
+
     /// Stages the changes, commits them, and handles the Git repository
-    pub fn stage(&self, message: &Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+    ///
+    /// No commit is created when the staged tree is identical to the one at HEAD.
+    pub fn stage(&self, message: &Option<String>) -> Result<StageOutcome, Box<dyn std::error::Error>> {
         // Determine the commit message, use current timestamp if not provided
         let msg =
-            message.clone().unwrap_or_else(|| Local::now().format("%Y-%m-%d %H:%M:%S").to_string());
+            message.clone().unwrap_or_else(|| Local::now().format(TIMESTAMP_FORMAT).to_string());
         println!("{}", msg);
 
         // Initialize the Git repository
@@ -167,36 +491,64 @@ impl Routine {
             Ok(repo) => repo,
             Err(_) => {
 		println!("No repository exists, initializing.");
-		Repository::init(path).expect("Failed to create repository")
+		// Start the repository on the configured branch so it can be pushed as is
+		let mut options = RepositoryInitOptions::new();
+		options.initial_head(&self.git.branch);
+		Repository::init_opts(path, &options).map_err(|error| {
+		    format!("Failed to create a repository at {}: {}", path, error.message())
+		})?
             },
         };
 	
         let mut repo_index = repo.index()?;
+        let excluder = Excluder::new(Path::new(path), &self.advanced.exclude)?;
+        let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+        let codec = Codec::for_staging(&repo, head_tree.as_ref(), &self.crypt, &self.press)?;
 
-        // Get all file paths in the directory (excluding .git directories)
-        let file_paths = walk_directory(path);
-        let file_paths_string: String = file_paths
-            .iter()
-            .filter_map(|file| file.to_str())
-            .filter_map(|file_str| file_str.strip_prefix(path)) // Strip the base path
-            .collect::<Vec<_>>()
-            .join(" "); // Join all paths with a space
+        let method = self.base.interval.sync_method;
+        let delta = DeltaCache::prepare(&repo, &mut repo_index, method, &codec.fingerprint())?;
 
-        // Add the files to the Git index
-        repo_index.add_all([&file_paths_string], IndexAddOption::DEFAULT, None)?;
+        // Add every file in the directory (excluding .git directories and excluded files) to the
+        // Git index, by its path relative to the repository, skipping those known to be unchanged
+        let files = walk_directory(path, &excluder)
+            .into_iter()
+            .map(|file| file.strip_prefix(path).map(Path::to_path_buf))
+            .collect::<Result<HashSet<_>, _>>()?;
+        for file in &files {
+            let metadata = fs::symlink_metadata(Path::new(path).join(file))?;
+            if delta.is_unchanged(&repo_index, file, &metadata) {
+                continue;
+            }
+
+            if codec.is_plain() {
+                repo_index.add_path(file)?;
+            } else {
+                add_encoded(&repo, &mut repo_index, Path::new(path), file, &codec)?;
+            }
+        }
+
+        // Drop files that were deleted, renamed or excluded since the last stage (0 removes a
+        // path, 1 keeps it)
+        repo_index.remove_all(["*"], Some(&mut |file: &Path, _: &[u8]| files.contains(file) as i32))?;
+
+        // Encrypted repositories carry the header needed to derive their key
+        if let Some(header) = codec.header() {
+            let size = repo.find_blob(header)?.size();
+            add_blob(&mut repo_index, CRYPT_HEADER_FILE_NAME, header, 0o100644, size)?;
+        }
         repo_index.write()?; // Write the changes to the index
 
         // Create a commit author signature
-	/*TODO allow the user to change this*/
-        let committer = Signature::now("test", "test@example.com")?;
+        let committer = self.signature(&repo)?;
         let tree_oid = repo_index.write_tree()?; // Write the index as a tree
         let tree = repo.find_tree(tree_oid)?;
 
-        // If there is no HEAD, initialize the repository with the first commit
+        // If there is no HEAD, initialize the repository with an empty first commit
         let head_real = match repo.head() {
             Ok(head) => head,
             Err(_) => {
-                init_repo(&repo, &committer, &tree)?; // Initialize the repo if no HEAD exists
+                let empty_tree = repo.find_tree(repo.treebuilder(None)?.write()?)?;
+                init_repo(&repo, &committer, &empty_tree)?; // Initialize the repo if no HEAD exists
                 repo.head()?
             },
         };
@@ -204,6 +556,12 @@ impl Routine {
         // Get the parent commit (the current latest commit)
         let parent_commit = head_real.peel_to_commit()?;
 
+        // Avoid filling the history with commits that change nothing
+        if parent_commit.tree_id() == tree_oid {
+            println!("Nothing to sync.");
+            return Ok(StageOutcome::Unchanged);
+        }
+
         // Create a new commit with the changes
         let commit_oid = repo.commit(
             Some("HEAD"),
@@ -216,13 +574,314 @@ impl Routine {
 
         println!("New commit created: {}", commit_oid);
 
+        Ok(StageOutcome::Committed(commit_oid))
+    }
+
+    /// Writes the routine configuration back to a TOML file
+    pub fn write(&self, file_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        let routine_str = toml::to_string(self)?;
+        fs::write(file_path, routine_str)?;
+        Ok(())
+    }
+
+    /// Pushes the currently checked out branch to the routine's remote and branch
+    pub fn push(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let repo = Repository::open(&self.base.path)?;
+
+        // Push whatever HEAD points to onto the configured remote branch
+        let head = repo.head()?;
+        let local_ref = head.name().ok_or("HEAD is not a valid UTF-8 reference")?;
+        let refspec = format!(
+            "{}{}:refs/heads/{}",
+            if self.git.force_push { "+" } else { "" },
+            local_ref,
+            self.git.branch
+        );
+
+        // Rejected references do not fail the push itself, so collect them separately
+        let mut rejected = Vec::new();
+
+        let git_config = repo.config().or_else(|_| Config::open_default())?;
+        let mut callbacks = remote_callbacks(&git_config);
+        callbacks.push_update_reference(|reference, status| {
+            if let Some(message) = status {
+                rejected.push(format!("{}: {}", reference, message));
+            }
+            Ok(())
+        });
+
+        let mut options = PushOptions::new();
+        options.remote_callbacks(callbacks);
+
+        let mut remote = repo.remote_anonymous(&self.git.remote)?;
+        remote.push(&[&refspec], Some(&mut options))?;
+        drop(options);
+
+        if !rejected.is_empty() {
+            return Err(format!("Push rejected by remote: {}", rejected.join(", ")).into());
+        }
+
+        // Remember what the remote branch now points to, so unchanged routines skip the push
+        let head_oid = head.peel_to_commit()?.id();
+        repo.reference(&self.tracking_ref(), head_oid, true, "ACT-IV throw: push")?;
+
+        println!("Pushed {} to {} ({})", local_ref, self.git.remote, self.git.branch);
+
+        Ok(())
+    }
+
+    /// Returns the reference tracking the routine's remote branch
+    fn tracking_ref(&self) -> String {
+        format!("refs/remotes/origin/{}", self.git.branch)
+    }
+
+    /// Returns whether HEAD was already pushed to the remote branch
+    fn is_pushed(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        let repo = Repository::open(&self.base.path)?;
+        let head_oid = repo.head()?.peel_to_commit()?.id();
+
+        Ok(repo.refname_to_id(&self.tracking_ref()).is_ok_and(|pushed| pushed == head_oid))
+    }
+
+    /// Stages the changes and pushes them to the remote, returning what the sync did
+    ///
+    /// When nothing changed and the last commit was already pushed, the push is skipped and
+    /// the throw still counts as a successful sync. Either way, the outcome is notified.
+    pub fn throw(
+        &self,
+        message: &Option<String>,
+    ) -> Result<(StageOutcome, SyncReport), Box<dyn std::error::Error>> {
+        let result = self.throw_and_report(message);
+        self.notify("throw", result.as_ref().map(|(_, report)| report).map_err(|error| &**error));
+
+        result
+    }
+
+    fn throw_and_report(
+        &self,
+        message: &Option<String>,
+    ) -> Result<(StageOutcome, SyncReport), Box<dyn std::error::Error>> {
+        let outcome = self.stage(message)?;
+
+        if outcome == StageOutcome::Unchanged && self.is_pushed()? {
+            println!("Remote is up to date, skipping push.");
+        } else {
+            self.push()?;
+        }
+
+        let repo = Repository::open(&self.base.path)?;
+        let head = repo.head()?.peel_to_commit()?;
+        let changes = match outcome {
+            StageOutcome::Committed(_) => {
+                let parent_tree = head.parent(0).and_then(|parent| parent.tree()).ok();
+                FileChanges::between(&repo, parent_tree.as_ref(), &head.tree()?)?
+            },
+            StageOutcome::Unchanged => FileChanges::default(),
+        };
+
+        Ok((outcome, SyncReport { commit: head.id(), changes }))
+    }
+
+    /// Sends the notifications the routine asks for about the outcome of a sync
+    fn notify(&self, action: &str, result: Result<&SyncReport, &dyn std::error::Error>) {
+        let notification = Notification::new(&self.base.title, action, result);
+        let notifiers = std::iter::once(&self.ntfy as &dyn Notifier)
+            .chain(self.notifiers.iter().map(|notifier| notifier as &dyn Notifier));
+
+        // A sync is not undone because nobody could be told about it
+        for notifier in notifiers.filter(|notifier| notifier.wants(&notification)) {
+            if let Err(error) = notifier.send(&notification) {
+                eprintln!("Could not send notification: {}", error);
+            }
+        }
+    }
+
+    /// Retrieves the routine's files from its remote
+    ///
+    /// Creates a repository at the routine path when there is none yet, then fetches the
+    /// configured branch and fast-forwards the local branch and files to it, decrypting them
+    /// if the repository is encrypted. Either way, the outcome is notified.
+    pub fn catch(&self) -> Result<SyncReport, Box<dyn std::error::Error>> {
+        let result = self.catch_and_report();
+        self.notify("catch", result.as_ref().map_err(|error| &**error));
+
+        result
+    }
+
+    fn catch_and_report(&self) -> Result<SyncReport, Box<dyn std::error::Error>> {
+        let path = Path::new(&self.base.path);
+
+        let git_config = Config::open_default()?;
+        let mut fetch_options = FetchOptions::new();
+        fetch_options.remote_callbacks(remote_callbacks(&git_config));
+
+        let repo = match Repository::open(path) {
+            Ok(repo) => repo,
+            Err(_) => {
+                // Catching into an existing directory is only safe if there is nothing in it
+                if path.exists() && fs::read_dir(path)?.next().is_some() {
+                    return Err(format!(
+                        "{} already exists and is not a repository",
+                        path.display()
+                    )
+                    .into());
+                }
+
+                let mut options = RepositoryInitOptions::new();
+                options.initial_head(&self.git.branch);
+                Repository::init_opts(path, &options)?
+            },
+        };
+
+        // Fetch the remote branch into its remote tracking reference
+        let tracking_ref = self.tracking_ref();
+        let refspec = format!("+refs/heads/{}:{}", self.git.branch, tracking_ref);
+        repo.remote_anonymous(&self.git.remote)?.fetch(&[&refspec], Some(&mut fetch_options), None)?;
+
+        let fetched = repo.find_reference(&tracking_ref)?;
+        let fetched_commit = repo.reference_to_annotated_commit(&fetched)?;
+        let (analysis, _) = repo.merge_analysis(&[&fetched_commit])?;
+
+        if analysis.is_up_to_date() {
+            println!("Already up to date.");
+            return Ok(SyncReport { commit: fetched_commit.id(), changes: FileChanges::default() });
+        }
+        if !analysis.is_fast_forward() && !analysis.is_unborn() {
+            return Err(format!(
+                "Local history of {} has diverged from {} ({}), refusing to overwrite it",
+                path.display(),
+                self.git.remote,
+                self.git.branch
+            )
+            .into());
+        }
+
+        // Bring the files up to date, refusing to overwrite anything changed locally
+        let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+        let head_codec = Codec::for_tree(&repo, head_tree.as_ref(), &self.crypt, &self.press)?;
+        let target_tree = repo.find_commit(fetched_commit.id())?.tree()?;
+        let target_codec = Codec::for_tree(&repo, Some(&target_tree), &self.crypt, &self.press)?;
+        restore_tree(&repo, path, head_tree.as_ref(), &head_codec, &target_tree, &target_codec, false)?;
+
+        // Move the local branch onto the fetched commit
+        let branch_ref = format!("refs/heads/{}", self.git.branch);
+        repo.reference(&branch_ref, fetched_commit.id(), true, "ACT-IV catch: fast-forward")?;
+        repo.set_head(&branch_ref)?;
+
+        let mut index = repo.index()?;
+        index.read_tree(&target_tree)?;
+        index.write()?;
+
+        println!("Fast-forwarded {} to {}", path.display(), fetched_commit.id());
+
+        let changes = FileChanges::between(&repo, head_tree.as_ref(), &target_tree)?;
+        Ok(SyncReport { commit: fetched_commit.id(), changes })
+    }
+
+    /// Lists the commits reachable from HEAD, newest first
+    pub fn history(&self) -> Result<Vec<Snapshot>, Box<dyn std::error::Error>> {
+        let repo = Repository::open(&self.base.path)?;
+        let history = Self::walk_history(&repo)?;
+
+        history
+            .iter()
+            .enumerate()
+            .map(|(index, commit)| {
+                Ok(Snapshot {
+                    index,
+                    id: commit.as_object().short_id()?.as_str().unwrap_or_default().to_string(),
+                    time: snapshot_time(commit).format(TIMESTAMP_FORMAT).to_string(),
+                    message: commit.summary().unwrap_or_default().to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Walks the first-parent history of HEAD, newest first
+    fn walk_history(repo: &Repository) -> Result<Vec<Commit<'_>>, git2::Error> {
+        let mut walk = repo.revwalk()?;
+        walk.push_head()?;
+        walk.simplify_first_parent()?;
+        walk.set_sorting(Sort::TOPOLOGICAL)?;
+
+        walk.map(|oid| repo.find_commit(oid?)).collect()
+    }
+
+    /// Restores the routine's files to an earlier snapshot
+    ///
+    /// The target is either `N-ago` (the Nth commit before HEAD), a date selecting the latest
+    /// snapshot taken at or before it, or a commit id. The restored state is recorded as a new
+    /// commit on top of HEAD so the rewind itself can be undone.
+    pub fn wind(&self, target: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let repo = Repository::open(&self.base.path)?;
+        let history = Self::walk_history(&repo)?;
+
+        let snapshot = if let Some(count) = target.strip_suffix("-ago") {
+            let count: usize = count.parse().map_err(|_| format!("Invalid count: {}", target))?;
+            history
+                .get(count)
+                .cloned()
+                .ok_or_else(|| format!("History only has {} snapshots", history.len()))?
+        } else if let Some(date) = parse_date(target) {
+            history
+                .iter()
+                .find(|commit| snapshot_time(commit) <= date)
+                .cloned()
+                .ok_or_else(|| format!("No snapshot taken at or before {}", target))?
+        } else {
+            repo.revparse_single(target)?.peel_to_commit()?
+        };
+
+        // Record uncommitted work first so that nothing is lost by the rewind
+        self.stage(&Some(String::from("ACT-IV Wind: snapshot before rewind")))?;
+
+        let parent = repo.head()?.peel_to_commit()?;
+        let head_tree = parent.tree()?;
+        let head_codec = Codec::for_tree(&repo, Some(&head_tree), &self.crypt, &self.press)?;
+        let target_tree = snapshot.tree()?;
+        let target_codec = Codec::for_tree(&repo, Some(&target_tree), &self.crypt, &self.press)?;
+        restore_tree(&repo, Path::new(&self.base.path), Some(&head_tree), &head_codec, &target_tree, &target_codec, true)?;
+
+        let mut index = repo.index()?;
+        index.read_tree(&target_tree)?;
+        index.write()?;
+
+        let committer = self.signature(&repo)?;
+        let message = format!(
+            "ACT-IV Wind: rewind to {} ({})",
+            snapshot.as_object().short_id()?.as_str().unwrap_or_default(),
+            snapshot.summary().unwrap_or_default()
+        );
+        let commit_oid = repo.commit(
+            Some("HEAD"),
+            &committer,
+            &committer,
+            &message,
+            &target_tree,
+            &[&parent],
+        )?;
+
+        println!("Rewound {} to {}: {}", self.base.path, snapshot.id(), commit_oid);
+
+        Ok(())
+    }
+
+    /// Prints the routine's history as a table
+    pub fn print_history(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let table = Table::new(self.history()?)
+            .with(Settings::default().with(Style::rounded()))
+            .to_string();
+
+        println!("{}", table);
+
         Ok(())
     }
 
-    /// Prints the routine configuration as a table
-    pub fn print(&self) {
+    /// Prints the routine configuration and run state as a table, with sensitive values masked
+    /// unless `reveal` is set
+    pub fn print(&self, state: &RoutineState, reveal: bool) {
 
-        let data = self.get_data();
+        let data = self.get_data(state, reveal);
 
         // Configure the table styling and display it
 	/*TODO Option to disable the pretty table printing*/
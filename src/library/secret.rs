@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize, Serializer};
+use std::cell::Cell;
+use std::error::Error;
+use std::fmt;
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+use wait_timeout::ChildExt;
+use zeroize::{Zeroize, Zeroizing};
+
+/// What secrets are replaced with whenever they are displayed
+pub(crate) const MASK: &str = "********";
+
+/// A sensitive value of a routine, such as a password or a token
+///
+/// It is stored in routine files as a plain string, but its `Display` and `Debug` output is
+/// masked, so printing a routine in any format never leaks it by accident. The actual value is
+/// only available through `expose`, and is zeroed when dropped.
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+thread_local! {
+    /// Whether secrets are serialized masked on this thread, see `masked`
+    static MASKING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `f` with every secret it serializes masked, to tell secrets apart in serialized routines
+pub(crate) fn masked<T>(f: impl FnOnce() -> T) -> T {
+    // Stops masking even if `f` panics
+    struct Unmask;
+    impl Drop for Unmask {
+        fn drop(&mut self) {
+            MASKING.with(|masking| masking.set(false));
+        }
+    }
+
+    MASKING.with(|masking| masking.set(true));
+    let _unmask = Unmask;
+    f()
+}
+
+impl Secret {
+    /// Returns the actual value, to be used rather than displayed
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the value to display, masked unless `reveal` is set
+    pub fn display(&self, reveal: bool) -> String {
+        if reveal {
+            self.0.clone()
+        } else {
+            MASK.to_string()
+        }
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Secret(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Secret(value.to_string())
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if MASKING.with(Cell::get) {
+            serializer.serialize_str(MASK)
+        } else {
+            serializer.serialize_str(&self.0)
+        }
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(MASK)
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({})", MASK)
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// How long a password command may run when the routine does not say otherwise
+pub(crate) const DEFAULT_EVAL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Runs a password command and returns what it printed, without surrounding whitespace
+///
+/// The command is split into a program and its arguments with shell quoting rules but run
+/// directly, unless `shell` is set, in which case it is handed to the system shell as is so
+/// that pipes and variables work. The output is only ever held in buffers that are zeroed
+/// when dropped.
+pub(crate) fn eval_password(
+    command: &str,
+    shell: bool,
+    timeout: Duration,
+) -> Result<Zeroizing<String>, Box<dyn Error>> {
+    let mut child = build_command(command, shell)?
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("Could not run the password command: {}", error))?;
+
+    // Read the output while waiting, so that a chatty command cannot fill the pipe and hang
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let stdout_reader = thread::spawn(move || {
+        let mut output = Zeroizing::new(Vec::with_capacity(1024));
+        stdout.read_to_end(&mut output).map(|_| output)
+    });
+    let stderr_reader = thread::spawn(move || {
+        let mut output = Vec::new();
+        stderr.read_to_end(&mut output).map(|_| output)
+    });
+
+    let Some(status) = child.wait_timeout(timeout)? else {
+        // The readers are left behind, as processes spawned by the command may keep the pipes open
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(format!("The password command timed out after {}s", timeout.as_secs()).into());
+    };
+
+    let output = stdout_reader.join().map_err(|_| "Could not read the password command output")??;
+    if !status.success() {
+        let stderr = stderr_reader.join().ok().and_then(Result::ok).unwrap_or_default();
+        return Err(format!(
+            "The password command failed with {}: {}",
+            status,
+            String::from_utf8_lossy(&stderr).trim()
+        )
+        .into());
+    }
+
+    let output = std::str::from_utf8(&output)
+        .map_err(|_| "The password command printed invalid UTF-8")?;
+    let password = Zeroizing::new(output.trim().to_string());
+    if password.is_empty() {
+        return Err("The password command printed nothing".into());
+    }
+
+    Ok(password)
+}
+
+/// Builds the command to run, through the system shell or directly
+fn build_command(command: &str, shell: bool) -> Result<Command, Box<dyn Error>> {
+    if shell {
+        let mut process = if cfg!(windows) { Command::new("cmd") } else { Command::new("sh") };
+        process.arg(if cfg!(windows) { "/C" } else { "-c" }).arg(command);
+        return Ok(process);
+    }
+
+    let words = shell_words::split(command)
+        .map_err(|error| format!("Invalid password command {:?}: {}", command, error))?;
+    let (program, arguments) = words.split_first().ok_or("The password command is empty")?;
+
+    let mut process = Command::new(program);
+    process.args(arguments);
+    Ok(process)
+}
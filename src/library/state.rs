@@ -0,0 +1,116 @@
+use chrono::{DateTime, Utc};
+use git2::Oid;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::library::config::{write_atomic, Configuration};
+use crate::library::routine::BaseInterval;
+
+/// Directory of the state store, inside the configuration directory
+const STATE_DIR_NAME: &str = "state";
+
+/// What happened the last times a routine was thrown or caught
+///
+/// It is kept apart from the routine file, which is written by hand and never rewritten by
+/// ACT-IV, so that recording a sync cannot clobber its comments or formatting.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct RoutineState {
+    pub last_success: Option<DateTime<Utc>>, // Time of the last successful sync
+    pub last_attempt: Option<DateTime<Utc>>, // Time of the last sync, whatever its outcome
+    pub last_error: Option<String>, // Error of the last sync, if it failed
+    pub last_commit: Option<String>, // Commit the routine was at after its last successful sync
+    #[serde(default)]
+    pub consecutive_failures: u32, // Number of syncs that failed since the last success
+}
+
+impl RoutineState {
+    /// Records the outcome of a sync attempted at `now`, the commit it ended at or its error
+    pub fn record(&mut self, now: DateTime<Utc>, result: Result<Oid, &dyn Error>) {
+        self.last_attempt = Some(now);
+
+        match result {
+            Ok(commit) => {
+                self.last_success = Some(now);
+                self.last_error = None;
+                self.last_commit = Some(commit.to_string());
+                self.consecutive_failures = 0;
+            },
+            Err(error) => {
+                self.last_error = Some(error.to_string());
+                self.consecutive_failures += 1;
+            },
+        }
+    }
+
+    /// Returns the time of the last successful sync
+    ///
+    /// Routines that were never synced since the state store exists fall back to the
+    /// `last_sync_time` written in their file.
+    pub fn last_sync(&self, interval: &BaseInterval) -> Option<DateTime<Utc>> {
+        self.last_success.or_else(|| interval.last_sync())
+    }
+}
+
+/// Layout of a state file, naming the routine file it belongs to for whoever reads it
+#[derive(Deserialize, Serialize)]
+struct StateFile {
+    routine: PathBuf,
+    #[serde(flatten)]
+    state: RoutineState,
+}
+
+/// Where the state of every routine is kept, one file per routine
+pub(crate) struct StateStore {
+    dir: PathBuf,
+}
+
+impl StateStore {
+    /// Opens the state store of the configuration directory
+    pub(crate) fn new() -> Result<Self, Box<dyn Error>> {
+        Ok(Self::at(Configuration::get_dir()?.join(STATE_DIR_NAME)))
+    }
+
+    /// Opens a state store kept in the given directory
+    pub(crate) fn at(dir: PathBuf) -> Self {
+        StateStore { dir }
+    }
+
+    /// Returns the state of the routine stored at `routine_path`, empty if it was never synced
+    pub(crate) fn load(&self, routine_path: &Path) -> Result<RoutineState, Box<dyn Error>> {
+        let content = match fs::read_to_string(self.state_path(routine_path)) {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(RoutineState::default()),
+            Err(error) => return Err(error.into()),
+        };
+
+        Ok(toml::from_str::<StateFile>(&content)?.state)
+    }
+
+    /// Saves the state of the routine stored at `routine_path`
+    ///
+    /// The state is written atomically, so that it is never left half written.
+    pub(crate) fn save(&self, routine_path: &Path, state: &RoutineState) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(&self.dir)?;
+
+        let content = toml::to_string(&StateFile {
+            routine: canonical(routine_path),
+            state: state.clone(),
+        })?;
+
+        write_atomic(&self.state_path(routine_path), &content)
+    }
+
+    /// Path of the state file of a routine, named after a hash of its routine file's path
+    fn state_path(&self, routine_path: &Path) -> PathBuf {
+        let digest = Sha256::digest(canonical(routine_path).to_string_lossy().as_bytes());
+        self.dir.join(format!("{}.toml", hex::encode(&digest[..16])))
+    }
+}
+
+/// Resolves the path of a routine file, so that every way of naming it finds the same state
+fn canonical(routine_path: &Path) -> PathBuf {
+    dunce::canonicalize(routine_path).unwrap_or_else(|_| routine_path.to_path_buf())
+}
@@ -1,6 +1,6 @@
 use clap::Parser;
-use cli::{Args, Commands};
-use commands::{catch, info, stage, sync, throw, wind};
+use cli::{Args, Commands, ConfigAction};
+use commands::{catch, clock, config, info, list, rename, stage, sync, throw, wind};
 use crate::commands::init;
 
 mod cli;
@@ -14,12 +14,21 @@ fn main() {
 
     // Match the parsed command and delegate the execution
     match &args.command {
-        Commands::Info { routine } => info::info(routine),
-        Commands::Stage { routine, message } => stage::stage(routine, message),
+        Commands::Info { routines, reveal, yes } => info::info(routines, *reveal, *yes),
+        Commands::Stage { routines, message } => stage::stage(routines, message),
+        Commands::List { failing, due, json } => list::list(*failing, *due, *json),
         Commands::Sync => sync::sync(),
-        Commands::Throw {} => throw::throw(),
-        Commands::Catch => catch::catch(),
-        Commands::Wind => wind::wind(),
-        Commands::Init { name } => init::init(name),
+        Commands::Rename { routine, name } => rename::rename(routine, name),
+        Commands::Throw { routines, message } => throw::throw(routines, message),
+        Commands::Catch { routines } => catch::catch(routines),
+        Commands::Wind { routines, to } => wind::wind(routines, to),
+        Commands::Config { action } => match action {
+            ConfigAction::Get { routine, key, reveal, yes } => config::get(routine, key, *reveal, *yes),
+            ConfigAction::Set { routine, key, value } => config::set(routine, key, value),
+        },
+        Commands::Clock => clock::clock(),
+        Commands::Init { name, remote, branch, interval, exclude } => {
+            init::init(name, remote, branch, interval, exclude)
+        },
     }
 }
@@ -1,4 +1,14 @@
 mod routine;
 mod cli;
 mod lister;
-mod config;
\ No newline at end of file
+mod config;
+mod daemon;
+mod interval;
+mod exclude;
+mod crypt;
+mod secret;
+mod press;
+mod delta;
+mod notify;
+mod state;
+mod document;
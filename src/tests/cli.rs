@@ -1,8 +1,7 @@
 #[cfg(test)]
 mod cli_tests {
-    use std::path::PathBuf;
     // Importing the code under test
-    use crate::cli::{Args, Commands};
+    use crate::cli::{Args, Commands, ConfigAction};
     use clap::Parser;
 
     #[test]
@@ -12,22 +11,38 @@ mod cli_tests {
         let parsed = Args::parse_from(args);
 
         // Assert the correct command is parsed
-        if let Commands::Info { routine } = parsed.command {
-            assert_eq!(routine, PathBuf::from("routine_path"));
+        if let Commands::Info { routines, reveal, .. } = parsed.command {
+            assert_eq!(routines.routine.unwrap(), "routine_path");
+            assert!(!reveal);
         } else {
             panic!("Expected `info` command");
         }
     }
 
+    #[test]
+    fn test_info_command_reveal() {
+        let parsed = Args::parse_from(vec!["cli_tool", "info", "routine_path", "--reveal", "-y"]);
+
+        if let Commands::Info { reveal, yes, .. } = parsed.command {
+            assert!(reveal);
+            assert!(yes);
+        } else {
+            panic!("Expected `info` command");
+        }
+
+        // Skipping the confirmation only makes sense when revealing
+        assert!(Args::try_parse_from(vec!["cli_tool", "info", "routine_path", "--yes"]).is_err());
+    }
+
     #[test]
     fn test_stage_command_with_message() {
         // Simulating the `stage` command with routine and a message
-        let args = vec!["cli_tool", "stage", "routine_path", "Test commit"];
+        let args = vec!["cli_tool", "stage", "routine_path", "-m", "Test commit"];
         let parsed = Args::parse_from(args);
 
         // Assert the correct command is parsed and message is set
-        if let Commands::Stage { routine, message } = parsed.command {
-            assert_eq!(routine, PathBuf::from("routine_path"));
+        if let Commands::Stage { routines, message } = parsed.command {
+            assert_eq!(routines.routine.unwrap(), "routine_path");
             assert_eq!(message.unwrap(), "Test commit");
         } else {
             panic!("Expected `stage` command");
@@ -41,8 +56,8 @@ mod cli_tests {
         let parsed = Args::parse_from(args);
 
         // Assert the correct command is parsed and the message is None
-        if let Commands::Stage { routine, message } = parsed.command {
-            assert_eq!(routine, PathBuf::from("routine_path"));
+        if let Commands::Stage { routines, message } = parsed.command {
+            assert_eq!(routines.routine.unwrap(), "routine_path");
             assert!(message.is_none());
         } else {
             panic!("Expected `stage` command");
@@ -51,27 +66,75 @@ mod cli_tests {
 
     #[test]
     fn test_throw_command() {
-        // Simulating the `throw` command with no arguments
-        let args = vec!["cli_tool", "throw"];
+        // Simulating the `throw` command with a routine argument
+        let args = vec!["cli_tool", "throw", "routine_path"];
         let parsed = Args::parse_from(args);
 
         // Assert the correct command is parsed
-        if let Commands::Throw {} = parsed.command {
-            // Test passed
+        if let Commands::Throw { routines, message } = parsed.command {
+            assert_eq!(routines.routine.unwrap(), "routine_path");
+            assert!(message.is_none());
+        } else {
+            panic!("Expected `throw` command");
+        }
+    }
+
+    #[test]
+    fn test_throw_command_requires_routine() {
+        // Simulating the `throw` command without the routine argument
+        let args = vec!["cli_tool", "throw"];
+
+        assert!(Args::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn test_all_with_message() {
+        // The message is an option, so it cannot be taken for the routine
+        let parsed = Args::parse_from(vec!["cli_tool", "stage", "--all", "--message", "Weekly"]);
+        if let Commands::Stage { routines, message } = parsed.command {
+            assert!(routines.all);
+            assert!(routines.routine.is_none());
+            assert_eq!(message.as_deref(), Some("Weekly"));
+        } else {
+            panic!("Expected `stage` command");
+        }
+
+        let parsed = Args::parse_from(vec!["cli_tool", "throw", "--all", "-m", "Weekly"]);
+        if let Commands::Throw { routines, message } = parsed.command {
+            assert!(routines.all);
+            assert_eq!(message.as_deref(), Some("Weekly"));
+        } else {
+            panic!("Expected `throw` command");
+        }
+
+        assert!(Args::try_parse_from(vec!["cli_tool", "throw", "--all", "Weekly"]).is_err());
+    }
+
+    #[test]
+    fn test_throw_command_all() {
+        let parsed = Args::parse_from(vec!["cli_tool", "throw", "--all"]);
+
+        if let Commands::Throw { routines, message } = parsed.command {
+            assert!(routines.all);
+            assert!(routines.routine.is_none());
+            assert!(message.is_none());
         } else {
             panic!("Expected `throw` command");
         }
+
+        // Naming a routine and asking for all of them is ambiguous
+        assert!(Args::try_parse_from(vec!["cli_tool", "throw", "Notes", "--all"]).is_err());
     }
 
     #[test]
     fn test_catch_command() {
-        // Simulating the `catch` command with no arguments
-        let args = vec!["cli_tool", "catch"];
+        // Simulating the `catch` command with a routine argument
+        let args = vec!["cli_tool", "catch", "routine_path"];
         let parsed = Args::parse_from(args);
 
         // Assert the correct command is parsed
-        if let Commands::Catch = parsed.command {
-            // Test passed
+        if let Commands::Catch { routines } = parsed.command {
+            assert_eq!(routines.routine.unwrap(), "routine_path");
         } else {
             panic!("Expected `catch` command");
         }
@@ -79,15 +142,97 @@ mod cli_tests {
 
     #[test]
     fn test_wind_command() {
-        // Simulating the `wind` command with no arguments
-        let args = vec!["cli_tool", "wind"];
+        // Simulating the `wind` command without a target
+        let args = vec!["cli_tool", "wind", "routine_path"];
         let parsed = Args::parse_from(args);
 
         // Assert the correct command is parsed
-        if let Commands::Wind = parsed.command {
-            // Test passed
+        if let Commands::Wind { routines, to } = parsed.command {
+            assert_eq!(routines.routine.unwrap(), "routine_path");
+            assert!(to.is_none());
+        } else {
+            panic!("Expected `wind` command");
+        }
+    }
+
+    #[test]
+    fn test_wind_command_with_target() {
+        // Simulating the `wind` command with a target snapshot
+        let args = vec!["cli_tool", "wind", "routine_path", "--to", "2-ago"];
+        let parsed = Args::parse_from(args);
+
+        if let Commands::Wind { to, .. } = parsed.command {
+            assert_eq!(to.unwrap(), "2-ago");
         } else {
             panic!("Expected `wind` command");
         }
     }
+
+    #[test]
+    fn test_init_command_with_flags() {
+        // Simulating the `init` command with a name and provisioning flags
+        let args = vec![
+            "cli_tool", "init", "Notes", "--remote", "git@example.com:notes", "--interval", "6h",
+            "--exclude", "*.tmp", "--exclude", "cache/",
+        ];
+        let parsed = Args::parse_from(args);
+
+        if let Commands::Init { name, remote, branch, interval, exclude } = parsed.command {
+            assert_eq!(name.unwrap(), "Notes");
+            assert_eq!(remote.unwrap(), "git@example.com:notes");
+            assert!(branch.is_none());
+            assert_eq!(interval.unwrap().to_string(), "6h");
+            assert_eq!(exclude, vec!["*.tmp", "cache/"]);
+        } else {
+            panic!("Expected `init` command");
+        }
+    }
+
+    #[test]
+    fn test_config_commands() {
+        let parsed = Args::parse_from(vec!["cli_tool", "config", "set", "routine_path", "git.branch", "dev"]);
+
+        if let Commands::Config { action: ConfigAction::Set { routine, key, value } } = parsed.command {
+            assert_eq!(routine, "routine_path");
+            assert_eq!(key, "git.branch");
+            assert_eq!(value, "dev");
+        } else {
+            panic!("Expected `config set` command");
+        }
+
+        // The key is optional when reading, to print the whole routine
+        let parsed = Args::parse_from(vec!["cli_tool", "config", "get", "routine_path"]);
+
+        if let Commands::Config { action: ConfigAction::Get { key, reveal, .. } } = parsed.command {
+            assert!(key.is_none());
+            assert!(!reveal);
+        } else {
+            panic!("Expected `config get` command");
+        }
+    }
+
+    #[test]
+    fn test_list_command_filters() {
+        let parsed = Args::parse_from(vec!["cli_tool", "list", "--failing", "--json"]);
+
+        if let Commands::List { failing, due, json } = parsed.command {
+            assert!(failing);
+            assert!(!due);
+            assert!(json);
+        } else {
+            panic!("Expected `list` command");
+        }
+    }
+
+    #[test]
+    fn test_rename_command() {
+        let parsed = Args::parse_from(vec!["cli_tool", "rename", "Notes", "Journal"]);
+
+        if let Commands::Rename { routine, name } = parsed.command {
+            assert_eq!(routine, "Notes");
+            assert_eq!(name, "Journal");
+        } else {
+            panic!("Expected `rename` command");
+        }
+    }
 }
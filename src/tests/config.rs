@@ -1,4 +1,64 @@
 #[cfg(test)]
 mod config_tests {
+    use crate::library::config::{Configuration, CONFIG_FILE_NAME};
+    use crate::library::routine::Routine;
+    use git2::Repository;
+    use std::fs;
+    use tempfile::{tempdir, TempDir};
 
-}
\ No newline at end of file
+    // Helper function to create a repository whose own git config sets an identity
+    fn create_repo() -> (TempDir, Repository) {
+        let work_dir = tempdir().unwrap();
+        let repo = Repository::init(work_dir.path()).unwrap();
+        let mut git_config = repo.config().unwrap();
+        git_config.set_str("user.name", "Git User").unwrap();
+        git_config.set_str("user.email", "git@example.com").unwrap();
+        (work_dir, repo)
+    }
+
+    #[test]
+    fn test_signature_prefers_routine_identity() {
+        let (work_dir, repo) = create_repo();
+        let mut routine = Routine::new("Notes".to_string(), work_dir.path().display().to_string());
+        routine.git.author_name = Some("Routine User".to_string());
+        routine.git.author_email = Some("routine@example.com".to_string());
+
+        let signature = routine.signature(&repo).unwrap();
+
+        assert_eq!(signature.name(), Some("Routine User"));
+        assert_eq!(signature.email(), Some("routine@example.com"));
+    }
+
+    #[test]
+    fn test_signature_falls_back_to_global_then_git_config() {
+        let (work_dir, repo) = create_repo();
+        let mut routine = Routine::new("Notes".to_string(), work_dir.path().display().to_string());
+        routine.git.author_email = Some("routine@example.com".to_string());
+
+        // Without a global config, the repository's git config is used
+        let config_dir = tempdir().unwrap();
+        let global_config = Configuration::read_from(config_dir.path()).unwrap_or_default();
+        assert_eq!(routine.signature_with(&repo, &global_config).unwrap().name(), Some("Git User"));
+
+        // The global config takes precedence over git config, field by field
+        fs::write(config_dir.path().join(CONFIG_FILE_NAME), "author_name = \"Global User\"\n").unwrap();
+        let global_config = Configuration::read_from(config_dir.path()).unwrap();
+        let signature = routine.signature_with(&repo, &global_config).unwrap();
+
+        assert_eq!(signature.name(), Some("Global User"));
+        assert_eq!(signature.email(), Some("routine@example.com"));
+    }
+
+    #[test]
+    fn test_signature_appends_hostname() {
+        let (work_dir, repo) = create_repo();
+        let mut routine = Routine::new("Notes".to_string(), work_dir.path().display().to_string());
+        routine.git.author_name = Some("Routine User".to_string());
+        routine.git.append_hostname = true;
+
+        let hostname = gethostname::gethostname().to_string_lossy().to_string();
+        let signature = routine.signature(&repo).unwrap();
+
+        assert_eq!(signature.name().unwrap(), format!("Routine User ({})", hostname));
+    }
+}
@@ -0,0 +1,128 @@
+#[cfg(test)]
+mod crypt_tests {
+    use crate::library::crypt::{CryptHeader, CRYPT_HEADER_FILE_NAME};
+    use crate::library::routine::{Routine, StageOutcome};
+    use git2::Repository;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    // Helper function to create an encrypted routine syncing `path` to `remote`
+    fn create_routine(path: &Path, remote: &Path, password: &str) -> Routine {
+        let mut routine = Routine::new("Notes".to_string(), path.display().to_string());
+        routine.git.remote = remote.display().to_string();
+        routine.crypt.encrypt = true;
+        routine.crypt.password = Some(password.into());
+        routine
+    }
+
+    #[test]
+    fn test_cipher_round_trip() {
+        let (header, cipher) = CryptHeader::create("secret").unwrap();
+
+        // Encryption is deterministic, and reversible by anyone knowing the password
+        let encrypted = cipher.encrypt(b"my notes", b"notes.txt");
+        assert_eq!(encrypted, cipher.encrypt(b"my notes", b"notes.txt"));
+        assert!(!encrypted.windows(8).any(|window| window == b"my notes"));
+
+        let header = CryptHeader::parse(&header.to_bytes().unwrap()).unwrap();
+        let cipher = header.unlock("secret").unwrap();
+        assert_eq!(cipher.decrypt(&encrypted, b"notes.txt").unwrap(), b"my notes");
+
+        assert!(header.unlock("wrong").is_err());
+
+        // Tampered data is rejected rather than decrypted to garbage
+        let mut tampered = encrypted.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(cipher.decrypt(&tampered, b"notes.txt").is_err());
+
+        // Data only decrypts for the file it was encrypted for
+        assert!(cipher.decrypt(&encrypted, b"other.txt").is_err());
+        assert_ne!(encrypted, cipher.encrypt(b"my notes", b"other.txt"));
+    }
+
+    #[test]
+    fn test_encrypted_throw_and_catch() {
+        let remote_dir = tempdir().unwrap();
+        Repository::init_bare(remote_dir.path()).unwrap();
+
+        let work_dir = tempdir().unwrap();
+        fs::create_dir(work_dir.path().join("private")).unwrap();
+        fs::write(work_dir.path().join("private/diary.txt"), "dear diary").unwrap();
+        let thrower = create_routine(work_dir.path(), remote_dir.path(), "secret");
+        thrower.throw(&None).unwrap();
+
+        // The remote only ever sees ciphertext, next to the plain header
+        let remote = Repository::open_bare(remote_dir.path()).unwrap();
+        let tree = remote.revparse_single("refs/heads/main").unwrap().peel_to_tree().unwrap();
+        let entry = tree.get_path(Path::new("private/diary.txt")).unwrap();
+        let blob = remote.find_blob(entry.id()).unwrap();
+        assert!(!String::from_utf8_lossy(blob.content()).contains("dear diary"));
+        assert!(tree.get_name(CRYPT_HEADER_FILE_NAME).is_some());
+
+        // Catching with the wrong password fails without writing anything
+        let target_dir = tempdir().unwrap();
+        let target_path = target_dir.path().join("notes");
+        let error = create_routine(&target_path, remote_dir.path(), "wrong").catch().unwrap_err();
+        assert!(error.to_string().contains("Wrong password"));
+        assert!(!target_path.join("private/diary.txt").exists());
+
+        // The right password decrypts the files transparently
+        create_routine(&target_path, remote_dir.path(), "secret").catch().unwrap();
+        assert_eq!(fs::read_to_string(target_path.join("private/diary.txt")).unwrap(), "dear diary");
+        assert!(!target_path.join(CRYPT_HEADER_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn test_swapped_encrypted_files_are_rejected() {
+        let remote_dir = tempdir().unwrap();
+        Repository::init_bare(remote_dir.path()).unwrap();
+
+        let work_dir = tempdir().unwrap();
+        fs::write(work_dir.path().join("public.txt"), "hello").unwrap();
+        fs::write(work_dir.path().join("private.txt"), "dear diary").unwrap();
+        create_routine(work_dir.path(), remote_dir.path(), "secret").throw(&None).unwrap();
+
+        // Someone with access to the remote swaps the blobs of the two files
+        let remote = Repository::open_bare(remote_dir.path()).unwrap();
+        let commit = remote.revparse_single("refs/heads/main").unwrap().peel_to_commit().unwrap();
+        let tree = commit.tree().unwrap();
+        let public = tree.get_name("public.txt").unwrap();
+        let private = tree.get_name("private.txt").unwrap();
+        let mut builder = remote.treebuilder(Some(&tree)).unwrap();
+        builder.insert("public.txt", private.id(), private.filemode()).unwrap();
+        builder.insert("private.txt", public.id(), public.filemode()).unwrap();
+        let swapped = remote.find_tree(builder.write().unwrap()).unwrap();
+        let signature = commit.author();
+        remote
+            .commit(Some("refs/heads/main"), &signature, &signature, "Swap", &swapped, &[&commit])
+            .unwrap();
+
+        let target_dir = tempdir().unwrap();
+        let target_path = target_dir.path().join("notes");
+        let error = create_routine(&target_path, remote_dir.path(), "secret").catch().unwrap_err();
+        assert!(error.to_string().contains("Could not decrypt"), "{}", error);
+        assert!(!target_path.join("public.txt").exists());
+    }
+
+    #[test]
+    fn test_encrypted_stage_and_wind() {
+        let work_dir = tempdir().unwrap();
+        let routine = create_routine(work_dir.path(), work_dir.path(), "secret");
+        fs::write(work_dir.path().join("notes.txt"), "first").unwrap();
+
+        assert!(matches!(routine.stage(&None).unwrap(), StageOutcome::Committed(_)));
+        assert_eq!(routine.stage(&None).unwrap(), StageOutcome::Unchanged);
+
+        fs::write(work_dir.path().join("notes.txt"), "second").unwrap();
+        routine.stage(&None).unwrap();
+        routine.wind("1-ago").unwrap();
+
+        assert_eq!(fs::read_to_string(work_dir.path().join("notes.txt")).unwrap(), "first");
+
+        // Staging with another password would mix keys in the same repository
+        let mut other = create_routine(work_dir.path(), work_dir.path(), "other");
+        other.base.title = "Other".to_string();
+        assert!(other.stage(&None).is_err());
+    }
+}
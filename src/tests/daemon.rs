@@ -0,0 +1,238 @@
+#[cfg(test)]
+mod daemon_tests {
+    use crate::library::daemon::{Clock, Daemon, POLL_INTERVAL};
+    use crate::library::routine::Routine;
+    use crate::library::state::StateStore;
+    use chrono::{DateTime, Duration, SecondsFormat, TimeZone, Utc};
+    use git2::Repository;
+    use std::cell::Cell;
+    use std::path::{Path, PathBuf};
+    use tempfile::{tempdir, TempDir};
+
+    // Clock whose time only moves when told to, or when slept on
+    struct FakeClock(Cell<DateTime<Utc>>);
+
+    impl Clock for FakeClock {
+        fn now(&self) -> DateTime<Utc> {
+            self.0.get()
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.0.set(self.0.get() + duration);
+        }
+    }
+
+    fn start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 12, 15, 12, 0, 0).unwrap()
+    }
+
+    // Helper function to write a routine syncing a fresh directory to a fresh bare remote
+    fn create_routine(
+        dir: &TempDir,
+        interval: &str,
+        last_sync_time: Option<DateTime<Utc>>,
+        sync_on_startup: bool,
+    ) -> PathBuf {
+        let work_dir = dir.path().join("work");
+        let remote_dir = dir.path().join("remote.git");
+        std::fs::create_dir(&work_dir).unwrap();
+        std::fs::write(work_dir.join("notes.txt"), "some notes").unwrap();
+        Repository::init_bare(&remote_dir).unwrap();
+
+        let mut routine = Routine::new("Notes".to_string(), format!("{}/", work_dir.display()));
+        routine.git.remote = remote_dir.display().to_string();
+        routine.base.interval.commit_interval = interval.parse().unwrap();
+        routine.base.interval.last_sync_time =
+            last_sync_time.map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true));
+        routine.advanced.sync_on_startup = sync_on_startup;
+
+        let routine_path = dir.path().join("ACT-Notes.toml");
+        routine.write(&routine_path).unwrap();
+        routine_path
+    }
+
+    // Helper function to keep the state of the routines in the test directory
+    fn state_store(dir: &TempDir) -> StateStore {
+        StateStore::at(dir.path().join("state"))
+    }
+
+    fn remote_commits(dir: &Path) -> usize {
+        let repo = Repository::open_bare(dir.join("remote.git")).unwrap();
+        let mut walk = repo.revwalk().unwrap();
+        walk.push_ref("refs/heads/main").unwrap();
+        walk.count()
+    }
+
+    #[test]
+    fn test_tick_throws_due_routines_once_per_interval() {
+        let dir = tempdir().unwrap();
+        let routine_path = create_routine(&dir, "1h", None, true);
+        let clock = FakeClock(Cell::new(start()));
+        let mut daemon = Daemon::from_paths(clock, state_store(&dir), std::slice::from_ref(&routine_path));
+
+        // A routine syncing on startup is due right away
+        let results = daemon.tick();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].1.is_ok());
+
+        // The sync is recorded in the state store, leaving the routine file untouched
+        let state = state_store(&dir).load(&routine_path).unwrap();
+        assert_eq!(state.last_success, Some(start()));
+        assert_eq!(state.last_attempt, Some(start()));
+        assert!(state.last_commit.is_some());
+        assert!(Routine::read(&routine_path).unwrap().base.interval.last_sync_time.is_none());
+
+        // It is not due again until its interval has passed
+        daemon.clock.sleep(Duration::minutes(59));
+        assert!(daemon.tick().is_empty());
+
+        daemon.clock.sleep(Duration::minutes(1));
+        assert_eq!(daemon.tick().len(), 1);
+    }
+
+    #[test]
+    fn test_tick_catches_up_on_missed_runs() {
+        let dir = tempdir().unwrap();
+        let routine_path = create_routine(&dir, "1d", Some(start() - Duration::days(3)), false);
+        let clock = FakeClock(Cell::new(start()));
+        let mut daemon = Daemon::from_paths(clock, state_store(&dir), &[routine_path]);
+
+        // Several missed intervals result in a single catch-up throw
+        assert_eq!(daemon.tick().len(), 1);
+        assert!(daemon.tick().is_empty());
+
+        // An initial commit plus the snapshot
+        assert_eq!(remote_commits(dir.path()), 2);
+    }
+
+    #[test]
+    fn test_step_sleeps_until_next_due() {
+        let dir = tempdir().unwrap();
+        let routine_path = create_routine(&dir, "30s", Some(start()), false);
+        let clock = FakeClock(Cell::new(start()));
+        let mut daemon = Daemon::from_paths(clock, state_store(&dir), &[routine_path]);
+
+        assert_eq!(daemon.time_to_next(), Duration::seconds(30));
+
+        // Sleeping in steps never overshoots the due time nor the poll interval
+        assert!(daemon.step().is_empty());
+        assert_eq!(daemon.clock.now(), start() + Duration::seconds(30));
+        assert_eq!(daemon.step().len(), 1);
+
+        daemon.routines[0].routine.base.interval.commit_interval = "1d".parse().unwrap();
+        assert_eq!(daemon.time_to_next(), POLL_INTERVAL);
+    }
+
+    #[test]
+    fn test_failed_throw_is_retried_later() {
+        let dir = tempdir().unwrap();
+        let routine_path = create_routine(&dir, "1h", None, true);
+        std::fs::remove_dir_all(dir.path().join("remote.git")).unwrap();
+        let clock = FakeClock(Cell::new(start()));
+        let mut daemon = Daemon::from_paths(clock, state_store(&dir), std::slice::from_ref(&routine_path));
+
+        let results = daemon.tick();
+        assert!(results[0].1.is_err());
+
+        // The failure is not retried on every tick
+        assert!(daemon.tick().is_empty());
+
+        let state = state_store(&dir).load(&routine_path).unwrap();
+        assert!(state.last_success.is_none());
+        assert_eq!(state.last_attempt, Some(start()));
+        assert!(state.last_error.is_some());
+        assert_eq!(state.consecutive_failures, 1);
+    }
+
+    #[test]
+    fn test_sync_on_startup_throws_routines_not_yet_due() {
+        let dir = tempdir().unwrap();
+        let routine_path = create_routine(&dir, "1d", Some(start() - Duration::hours(1)), true);
+        let clock = FakeClock(Cell::new(start()));
+        let mut daemon = Daemon::from_paths(clock, state_store(&dir), &[routine_path]);
+
+        assert_eq!(daemon.tick().len(), 1);
+        assert_eq!(daemon.routines[0].next_due(), start() + Duration::days(1));
+    }
+
+    #[test]
+    fn test_startup_waits_for_routines_not_yet_due() {
+        let dir = tempdir().unwrap();
+        let routine_path = create_routine(&dir, "1d", Some(start() - Duration::hours(1)), false);
+        let clock = FakeClock(Cell::new(start()));
+        let mut daemon = Daemon::from_paths(clock, state_store(&dir), &[routine_path]);
+
+        assert!(daemon.tick().is_empty());
+        assert_eq!(daemon.routines[0].next_due(), start() + Duration::hours(23));
+    }
+
+    #[test]
+    fn test_tick_syncs_routines_in_parallel() {
+        let dirs = (0..3).map(|_| tempdir().unwrap()).collect::<Vec<_>>();
+        let mut paths = dirs.iter().map(|dir| create_routine(dir, "1h", None, true)).collect::<Vec<_>>();
+
+        // A second routine on the first directory must wait for the first one to finish
+        let mut twin = Routine::read(&paths[0]).unwrap();
+        twin.base.title = "Twin".to_string();
+        twin.base.path = dirs[0].path().join("work").display().to_string();
+        let twin_path = dirs[0].path().join("ACT-Twin.toml");
+        twin.write(&twin_path).unwrap();
+        paths.push(twin_path);
+
+        let clock = FakeClock(Cell::new(start()));
+        let mut daemon = Daemon::from_paths(clock, state_store(&dirs[0]), &paths);
+        let results = daemon.tick();
+
+        let titles = results.iter().map(|(title, _)| title.as_str()).collect::<Vec<_>>();
+        assert_eq!(titles, ["Notes", "Notes", "Notes", "Twin"]);
+        assert!(results.iter().all(|(_, result)| result.is_ok()), "{:?}", results);
+        assert!(daemon.tick().is_empty());
+    }
+
+    #[test]
+    fn test_broken_routines_are_left_out() {
+        let dir = tempdir().unwrap();
+        let routine_path = create_routine(&dir, "1h", None, true);
+        let broken_path = dir.path().join("ACT-Broken.toml");
+        std::fs::write(&broken_path, "[base\ntitle = ").unwrap();
+
+        let clock = FakeClock(Cell::new(start()));
+        let mut daemon = Daemon::from_paths(clock, state_store(&dir), &[broken_path.clone(), routine_path]);
+
+        assert_eq!(daemon.routines.len(), 1);
+        assert_eq!(daemon.broken.len(), 1);
+        assert_eq!(daemon.broken[0].0, broken_path);
+        assert!(daemon.broken[0].1.contains("Invalid routine file"), "{}", daemon.broken[0].1);
+
+        let results = daemon.tick();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].1.is_ok(), "{:?}", results);
+    }
+
+    #[test]
+    fn test_failing_routine_does_not_stop_the_others() {
+        let dir = tempdir().unwrap();
+        let routine_path = create_routine(&dir, "1h", None, true);
+
+        // A routine syncing a plain file cannot get a repository
+        let not_a_dir = dir.path().join("not-a-dir");
+        std::fs::write(&not_a_dir, "").unwrap();
+        let mut failing = Routine::new("Fails".to_string(), not_a_dir.display().to_string());
+        failing.advanced.sync_on_startup = true;
+        let failing_path = dir.path().join("ACT-Fails.toml");
+        failing.write(&failing_path).unwrap();
+
+        let clock = FakeClock(Cell::new(start()));
+        let mut daemon = Daemon::from_paths(clock, state_store(&dir), &[failing_path, routine_path]);
+        let results = daemon.tick();
+
+        assert_eq!(results.len(), 2);
+        let error = results[0].1.as_ref().unwrap_err();
+        assert!(error.contains(&not_a_dir.display().to_string()), "{}", error);
+        assert!(results[1].1.is_ok(), "{:?}", results);
+
+        // The failing routine is retried later rather than on every tick
+        assert!(daemon.tick().is_empty());
+        assert!(daemon.routines[0].retry_at.is_some());
+    }
+}
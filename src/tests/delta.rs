@@ -0,0 +1,107 @@
+#[cfg(test)]
+mod delta_tests {
+    use crate::library::delta::SyncMethod;
+    use crate::library::routine::{Routine, StageOutcome};
+    use git2::Repository;
+    use std::fs::{self, File};
+    use std::path::Path;
+    use std::time::{Duration, UNIX_EPOCH};
+    use tempfile::tempdir;
+
+    // Helper function to write a file whose modification time is well before any stage
+    fn write_old(path: &Path, content: &str) {
+        fs::write(path, content).unwrap();
+        let old = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        File::options().write(true).open(path).unwrap().set_modified(old).unwrap();
+    }
+
+    // Helper function to read a committed file at HEAD
+    fn committed(path: &Path, name: &str) -> Option<Vec<u8>> {
+        let repo = Repository::open(path).unwrap();
+        let tree = repo.head().unwrap().peel_to_tree().unwrap();
+        let entry = tree.get_path(Path::new(name)).ok()?;
+        let content = repo.find_blob(entry.id()).unwrap().content().to_vec();
+        Some(content)
+    }
+
+    // Helper function to create a routine over `path` with the given sync method
+    fn create_routine(path: &Path, method: SyncMethod) -> Routine {
+        let mut routine = Routine::new("Data".to_string(), path.display().to_string());
+        routine.base.interval.sync_method = method;
+        routine
+    }
+
+    #[test]
+    fn test_parse_sync_method() {
+        let routine = Routine::new("Data".to_string(), "/tmp".to_string());
+        let content = toml::to_string(&routine).unwrap().replace("\"thorough\"", "\"delta\"");
+        let routine: Routine = toml::from_str(&content).unwrap();
+
+        assert_eq!(routine.base.interval.sync_method, SyncMethod::Delta);
+        assert!(toml::from_str::<Routine>(&content.replace("\"delta\"", "\"quick\"")).is_err());
+    }
+
+    #[test]
+    fn test_delta_does_not_read_unchanged_files() {
+        let work_dir = tempdir().unwrap();
+        for index in 0..100 {
+            write_old(&work_dir.path().join(format!("file-{:03}.txt", index)), "original");
+        }
+        let delta = create_routine(work_dir.path(), SyncMethod::Delta);
+        delta.stage(&None).unwrap();
+
+        // Same size and modification time: only a sync reading contents can see the change
+        write_old(&work_dir.path().join("file-000.txt"), "modified");
+        assert_eq!(delta.stage(&None).unwrap(), StageOutcome::Unchanged);
+        assert_eq!(committed(work_dir.path(), "file-000.txt").unwrap(), b"original");
+
+        // Files that were actually touched are picked up, and so are deletions
+        fs::write(work_dir.path().join("file-001.txt"), "changed!").unwrap();
+        fs::remove_file(work_dir.path().join("file-002.txt")).unwrap();
+        assert!(matches!(delta.stage(&None).unwrap(), StageOutcome::Committed(_)));
+        assert_eq!(committed(work_dir.path(), "file-001.txt").unwrap(), b"changed!");
+        assert_eq!(committed(work_dir.path(), "file-002.txt"), None);
+        assert_eq!(committed(work_dir.path(), "file-000.txt").unwrap(), b"original");
+
+        // A thorough sync reads everything again
+        let thorough = create_routine(work_dir.path(), SyncMethod::Thorough);
+        assert!(matches!(thorough.stage(&None).unwrap(), StageOutcome::Committed(_)));
+        assert_eq!(committed(work_dir.path(), "file-000.txt").unwrap(), b"modified");
+    }
+
+    #[test]
+    fn test_delta_skips_encoding_unchanged_files() {
+        let work_dir = tempdir().unwrap();
+        write_old(&work_dir.path().join("notes.txt"), "original");
+        write_old(&work_dir.path().join("other.txt"), "untouched");
+
+        let mut routine = create_routine(work_dir.path(), SyncMethod::Delta);
+        routine.press.compression = "gz".parse().unwrap();
+        routine.press.threshold = Some(0);
+        routine.stage(&None).unwrap();
+        let compressed = committed(work_dir.path(), "other.txt").unwrap();
+
+        write_old(&work_dir.path().join("notes.txt"), "modified");
+        assert_eq!(routine.stage(&None).unwrap(), StageOutcome::Unchanged);
+
+        fs::write(work_dir.path().join("notes.txt"), "modified again").unwrap();
+        assert!(matches!(routine.stage(&None).unwrap(), StageOutcome::Committed(_)));
+        assert_eq!(committed(work_dir.path(), "other.txt").unwrap(), compressed);
+    }
+
+    #[test]
+    fn test_delta_reencodes_after_codec_changes() {
+        let work_dir = tempdir().unwrap();
+        write_old(&work_dir.path().join("notes.txt"), &"line\n".repeat(1000));
+
+        let mut routine = create_routine(work_dir.path(), SyncMethod::Delta);
+        routine.stage(&None).unwrap();
+        assert_eq!(committed(work_dir.path(), "notes.txt").unwrap().len(), 5000);
+
+        // Although the file did not change, its committed form does
+        routine.press.compression = "gz".parse().unwrap();
+        routine.press.threshold = Some(0);
+        assert!(matches!(routine.stage(&None).unwrap(), StageOutcome::Committed(_)));
+        assert!(committed(work_dir.path(), "notes.txt").unwrap().len() < 5000);
+    }
+}
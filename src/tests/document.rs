@@ -0,0 +1,182 @@
+#[cfg(test)]
+mod document_tests {
+    use crate::library::document::RoutineDocument;
+    use crate::library::routine::Routine;
+    use std::path::PathBuf;
+    use tempfile::{tempdir, TempDir};
+
+    const ROUTINE: &str = r#"# Notes synced to the office
+[base]
+title = "Notes"
+path = "/path/to/notes"
+
+[base.interval]
+commit_interval = "1d"  # every day
+sync_method = "thorough"
+time_zone = "UTC"
+timestamp = true
+
+[crypt]
+encrypt = false
+password = "secretpassword"
+
+[press]
+compression = "nil"
+
+[advanced]
+exclude = ["*.tmp"]
+sync_on_startup = false
+
+[ntfy]
+ntfy_topic = ""
+notification_on_success = false
+notification_on_failure = false
+
+[git]
+remote = "git@example.com:notes" # the office server
+branch = "main"
+force_push = false
+append_hostname = false
+
+[[notify]]
+kind = "webhook"
+url = "https://example.com/hook?token=secret"
+"#;
+
+    // Helper function to write the routine above to a temporary file
+    fn write_routine(dir: &TempDir) -> PathBuf {
+        let path = dir.path().join("ACT-Notes.toml");
+        std::fs::write(&path, ROUTINE).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_set_keeps_comments_and_formatting() {
+        let dir = tempdir().unwrap();
+        let path = write_routine(&dir);
+
+        let mut document = RoutineDocument::open(&path).unwrap();
+        document.set("git.branch", "dev").unwrap();
+        document.set("base.interval.commit_interval", "6h").unwrap();
+        document.set("press.threshold", "2048").unwrap();
+        document.set("advanced.sync_on_startup", "true").unwrap();
+        document.save().unwrap();
+
+        // Only the changed values differ, comments stay in their column
+        let expected = ROUTINE
+            .replace("branch = \"main\"", "branch = \"dev\"")
+            .replace("commit_interval = \"1d\"  # every day", "commit_interval = \"6h\"  # every day")
+            .replace("compression = \"nil\"\n", "compression = \"nil\"\nthreshold = 2048\n")
+            .replace("sync_on_startup = false", "sync_on_startup = true");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), expected);
+
+        let routine = Routine::read(&path).unwrap();
+        assert_eq!(routine.git.branch, "dev");
+        assert_eq!(routine.press.threshold, Some(2048));
+        assert!(routine.advanced.sync_on_startup);
+    }
+
+    #[test]
+    fn test_set_realigns_trailing_comments() {
+        let dir = tempdir().unwrap();
+        let path = write_routine(&dir);
+
+        let mut document = RoutineDocument::open(&path).unwrap();
+        document.set("git.remote", "git@example.com:n").unwrap();
+
+        let expected = "remote = \"git@example.com:n\"     # the office server";
+        assert!(document.get(None, true).unwrap().contains(expected));
+    }
+
+    #[test]
+    fn test_set_refuses_invalid_settings() {
+        let dir = tempdir().unwrap();
+        let path = write_routine(&dir);
+        let mut document = RoutineDocument::open(&path).unwrap();
+
+        // Unknown keys, wrong types and tables are refused
+        assert!(document.set("git.remot", "git@example.com:notes").is_err());
+        assert!(document.set("press.threshold", "large").is_err());
+        assert!(document.set("git", "main").is_err());
+        assert!(document.set("notify.1.url", "https://example.com").is_err());
+
+        // Strings stay strings even when they look like other values
+        document.set("git.branch", "2024").unwrap();
+        assert_eq!(document.get(Some("git.branch"), false).unwrap(), "2024");
+
+        document.save().unwrap();
+        let expected = ROUTINE.replace("branch = \"main\"", "branch = \"2024\"");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_set_writes_string_settings_as_strings() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("ACT-Notes.toml");
+        std::fs::write(&path, ROUTINE.replace("password = \"secretpassword\"\n", "")).unwrap();
+
+        // Unset string settings are not read as numbers
+        let mut document = RoutineDocument::open(&path).unwrap();
+        document.set("crypt.password", "123456").unwrap();
+        document.set("git.author_name", "1984").unwrap();
+        document.save().unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("password = \"123456\""), "{}", content);
+        assert!(content.contains("author_name = \"1984\""), "{}", content);
+
+        let routine = Routine::read(&path).unwrap();
+        assert_eq!(routine.crypt.password.unwrap().expose(), "123456");
+        assert_eq!(routine.git.author_name.as_deref(), Some("1984"));
+    }
+
+    #[test]
+    fn test_get_masks_secrets() {
+        let dir = tempdir().unwrap();
+        let document = RoutineDocument::open(&write_routine(&dir)).unwrap();
+
+        assert_eq!(document.get(Some("git.remote"), false).unwrap(), "git@example.com:notes");
+        assert_eq!(document.get(Some("advanced.exclude"), false).unwrap(), "[\"*.tmp\"]");
+        assert_eq!(document.get(Some("crypt.password"), false).unwrap(), "********");
+        assert_eq!(document.get(Some("crypt.password"), true).unwrap(), "secretpassword");
+        assert!(document.get(Some("git.author_name"), false).is_err());
+
+        // Secrets are masked in tables and in the whole file as well
+        let whole = document.get(None, false).unwrap();
+        assert!(!whole.contains("secret"));
+        assert!(whole.contains("# Notes synced to the office"));
+        assert!(!document.get(Some("notify.0"), false).unwrap().contains("token"));
+        assert!(document.get(Some("notify.0.url"), true).unwrap().contains("token=secret"));
+    }
+
+    #[test]
+    fn test_get_masks_every_secret_field() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("ACT-Notes.toml");
+        let routine = ROUTINE
+            .replace("ntfy_topic = \"\"", "ntfy_topic = \"\"\ntoken = \"ntfy-token\"")
+            .replace("url = \"https://example.com/hook?token=secret\"", "url = \"https://example.com/hook\"\nheaders = { Authorization = \"Bearer abc\" }");
+        std::fs::write(&path, routine).unwrap();
+
+        // The secrets are those the routine holds as `Secret` values, wherever they are
+        let document = RoutineDocument::open(&path).unwrap();
+        assert_eq!(document.get(Some("ntfy.token"), false).unwrap(), "********");
+        assert_eq!(document.get(Some("notify.0.url"), false).unwrap(), "********");
+        assert_eq!(document.get(Some("notify.0.headers.Authorization"), false).unwrap(), "********");
+        assert_eq!(document.get(Some("notify.0.kind"), false).unwrap(), "webhook");
+        assert_eq!(document.get(Some("ntfy.token"), true).unwrap(), "ntfy-token");
+    }
+
+    #[test]
+    fn test_get_masks_every_string_of_an_invalid_routine() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("ACT-Broken.toml");
+        std::fs::write(&path, "[crypt]\nsecret_key = \"hunter2\"\nencrypt = true\n").unwrap();
+
+        // Without a valid routine the secrets cannot be told apart, so no string is shown
+        let document = RoutineDocument::open(&path).unwrap();
+        assert_eq!(document.get(Some("crypt.secret_key"), false).unwrap(), "********");
+        assert_eq!(document.get(Some("crypt.encrypt"), false).unwrap(), "true");
+        assert_eq!(document.get(Some("crypt.secret_key"), true).unwrap(), "hunter2");
+    }
+}
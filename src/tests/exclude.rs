@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod exclude_tests {
+    use crate::library::exclude::{Excluder, ACTIGNORE_FILE_NAME};
+    use crate::library::routine::{walk_directory, Routine};
+    use git2::Repository;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use tempfile::tempdir;
+
+    fn patterns(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|pattern| pattern.to_string()).collect()
+    }
+
+    #[test]
+    fn test_gitignore_semantics() {
+        let root = tempdir().unwrap();
+        let excluder =
+            Excluder::new(root.path(), &patterns(&["*.tmp", "!keep.tmp", "cache/", "**/build/*.o"]))
+                .unwrap();
+
+        assert!(excluder.is_excluded(Path::new("notes.tmp"), false));
+        assert!(excluder.is_excluded(Path::new("deep/down/notes.tmp"), false));
+        assert!(!excluder.is_excluded(Path::new("keep.tmp"), false));
+        assert!(!excluder.is_excluded(Path::new("notes.md"), false));
+
+        // Directory-only patterns exclude the directory and everything below it
+        assert!(excluder.is_excluded(Path::new("cache"), true));
+        assert!(excluder.is_excluded(Path::new("cache/data.bin"), false));
+        assert!(!excluder.is_excluded(Path::new("cache"), false));
+
+        assert!(excluder.is_excluded(Path::new("a/b/build/main.o"), false));
+        assert!(!excluder.is_excluded(Path::new("a/b/build/main.c"), false));
+
+        assert!(excluder.is_excluded(Path::new(".git/HEAD"), false));
+    }
+
+    #[test]
+    fn test_ignore_files_are_honoured() {
+        let root = tempdir().unwrap();
+        fs::write(root.path().join(".gitignore"), "*.log\n").unwrap();
+        fs::write(root.path().join(ACTIGNORE_FILE_NAME), "secrets/\n").unwrap();
+
+        // Routine patterns take precedence over the ignore files
+        let excluder = Excluder::new(root.path(), &patterns(&["!important.log"])).unwrap();
+
+        assert!(excluder.is_excluded(Path::new("debug.log"), false));
+        assert!(excluder.is_excluded(Path::new("secrets/key"), false));
+        assert!(!excluder.is_excluded(Path::new("important.log"), false));
+    }
+
+    #[test]
+    fn test_nested_ignore_files_are_honoured() {
+        let root = tempdir().unwrap();
+        fs::write(root.path().join(".gitignore"), "*.log\n/drafts/\n").unwrap();
+        fs::create_dir_all(root.path().join("docs/private")).unwrap();
+        fs::write(root.path().join("docs/.gitignore"), "*.pdf\n!keep.log\n").unwrap();
+        fs::write(root.path().join("docs/private").join(ACTIGNORE_FILE_NAME), "*\n").unwrap();
+        // Ignore files in excluded directories are not read
+        fs::create_dir_all(root.path().join("drafts")).unwrap();
+        fs::write(root.path().join("drafts/.gitignore"), "!*\n").unwrap();
+
+        let excluder = Excluder::new(root.path(), &patterns(&["docs/private/public.md"])).unwrap();
+
+        // Patterns of a subdirectory only apply below it, anchored to it
+        assert!(excluder.is_excluded(Path::new("docs/manual.pdf"), false));
+        assert!(excluder.is_excluded(Path::new("docs/deep/manual.pdf"), false));
+        assert!(!excluder.is_excluded(Path::new("manual.pdf"), false));
+
+        // Deeper ignore files take precedence over their parents, the routine over both
+        assert!(excluder.is_excluded(Path::new("docs/debug.log"), false));
+        assert!(!excluder.is_excluded(Path::new("docs/keep.log"), false));
+        assert!(excluder.is_excluded(Path::new("docs/private/notes.md"), false));
+        assert!(excluder.is_excluded(Path::new("drafts/post.md"), false));
+
+        let files = walk_directory(&root.path().display().to_string(), &excluder);
+        let mut relative = files
+            .iter()
+            .map(|file| file.strip_prefix(root.path()).unwrap().to_path_buf())
+            .collect::<Vec<_>>();
+        relative.sort();
+        assert_eq!(relative, vec![PathBuf::from(".gitignore"), PathBuf::from("docs/.gitignore")]);
+    }
+
+    #[test]
+    fn test_walk_directory_skips_excluded_paths() {
+        let root = tempdir().unwrap();
+        fs::create_dir_all(root.path().join("cache/nested")).unwrap();
+        fs::create_dir_all(root.path().join(".git")).unwrap();
+        fs::write(root.path().join("cache/nested/data.bin"), "").unwrap();
+        fs::write(root.path().join(".git/HEAD"), "").unwrap();
+        fs::write(root.path().join("notes.tmp"), "").unwrap();
+        fs::write(root.path().join("notes.md"), "").unwrap();
+
+        let path = format!("{}/", root.path().display());
+        let excluder = Excluder::new(root.path(), &patterns(&["*.tmp", "cache/"])).unwrap();
+        let walked = walk_directory(&path, &excluder);
+
+        assert_eq!(walked, vec![PathBuf::from(format!("{}notes.md", path))]);
+    }
+
+    #[test]
+    fn test_stage_drops_newly_excluded_files() {
+        let root = tempdir().unwrap();
+        fs::write(root.path().join("notes.tmp"), "scratch").unwrap();
+
+        let mut routine = Routine::new("Notes".to_string(), format!("{}/", root.path().display()));
+        routine.stage(&None).unwrap();
+
+        let repo = Repository::open(root.path()).unwrap();
+        let tree = repo.head().unwrap().peel_to_tree().unwrap();
+        assert!(tree.get_name("notes.tmp").is_some());
+
+        // Excluding a committed file removes it from the next snapshot, not from disk
+        routine.advanced.exclude = patterns(&["*.tmp"]);
+        routine.stage(&None).unwrap();
+
+        let tree = repo.head().unwrap().peel_to_tree().unwrap();
+        assert!(tree.get_name("notes.tmp").is_none());
+        assert!(root.path().join("notes.tmp").exists());
+    }
+}
@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod interval_tests {
+    use crate::library::interval::Interval;
+    use crate::library::routine::Routine;
+    use chrono::{Duration, TimeZone, Utc};
+    use chrono_tz::Tz;
+
+    // Returns the time between two runs of a duration shorthand
+    fn every(expression: &str) -> Duration {
+        let after = Utc.with_ymd_and_hms(2024, 12, 15, 12, 0, 0).unwrap();
+        let interval = expression.parse::<Interval>().unwrap();
+
+        interval.next_after(after, &Tz::UTC).unwrap() - after
+    }
+
+    #[test]
+    fn test_parse_duration_shorthands() {
+        assert_eq!(every("30s"), Duration::seconds(30));
+        assert_eq!(every("15m"), Duration::minutes(15));
+        assert_eq!(every("6h"), Duration::hours(6));
+        assert_eq!(every("1d"), Duration::days(1));
+        assert_eq!(every("2w"), Duration::weeks(2));
+
+        assert!("1x".parse::<Interval>().is_err());
+        assert!("d".parse::<Interval>().is_err());
+        assert!("0m".parse::<Interval>().is_err());
+    }
+
+    #[test]
+    fn test_parse_cron_expressions() {
+        assert!("0 9 * * Mon-Fri".parse::<Interval>().is_ok());
+        assert!("0 0 9 * * * *".parse::<Interval>().is_ok());
+        assert!("@daily".parse::<Interval>().is_ok());
+
+        assert!("0 99 * * *".parse::<Interval>().is_err());
+    }
+
+    #[test]
+    fn test_cron_uses_time_zone() {
+        let interval: Interval = "0 9 * * *".parse().unwrap();
+        let after = Utc.with_ymd_and_hms(2024, 12, 15, 12, 0, 0).unwrap();
+
+        // 09:00 in New York is 14:00 UTC in winter
+        let next = interval.next_after(after, &Tz::America__New_York).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2024, 12, 15, 14, 0, 0).unwrap());
+
+        let next = interval.next_after(after, &Tz::UTC).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2024, 12, 16, 9, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_routine_rejects_invalid_interval() {
+        let routine = r#"
+            [base]
+            title = "Notes"
+            path = "/path/to/notes"
+            interval = { commit_interval = "1x", sync_method = "thorough", timestamp = true, time_zone = "UTC" }
+
+            [crypt]
+            encrypt = false
+
+            [press]
+            compression = "nil"
+
+            [advanced]
+            exclude = []
+            sync_on_startup = false
+
+            [ntfy]
+            ntfy_topic = "sync-updates"
+            notification_on_success = false
+            notification_on_failure = false
+
+            [git]
+            remote = "https://github.com/exam/ple"
+            branch = "main"
+            force_push = false
+        "#;
+
+        let error = toml::from_str::<Routine>(routine).unwrap_err();
+        assert!(error.to_string().contains("1x"));
+
+        let routine = routine.replace("\"1x\"", "\"0 9 * * *\"");
+        let routine: Routine = toml::from_str(&routine).unwrap();
+        assert!(toml::to_string(&routine).unwrap().contains("commit_interval = \"0 9 * * *\""));
+    }
+
+    #[test]
+    fn test_upcoming_runs() {
+        let mut routine = Routine::new("Notes".to_string(), "/path/to/notes".to_string());
+        routine.base.interval.commit_interval = "6h".parse().unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 12, 15, 12, 0, 0).unwrap();
+
+        // Never synced, so the first run is right away
+        let runs = routine.base.interval.upcoming_runs(None, now, 3);
+        assert_eq!(runs, vec![now, now + Duration::hours(6), now + Duration::hours(12)]);
+
+        let last_sync = Utc.with_ymd_and_hms(2024, 12, 15, 10, 0, 0).unwrap();
+        let runs = routine.base.interval.upcoming_runs(Some(last_sync), now, 3);
+        assert_eq!(runs[0], Utc.with_ymd_and_hms(2024, 12, 15, 16, 0, 0).unwrap());
+        assert_eq!(runs.len(), 3);
+    }
+}
@@ -1,16 +1,24 @@
 #[cfg(test)]
 mod lister_tests {
     use std::fs;
-    use std::fs::remove_dir_all;
     use std::io::Write;
-    use std::path::{Path, PathBuf};
-    use crate::library::config::Configuration;
-    use crate::library::lister::{Lister, LISTER_FILE_NAME};
+    use std::path::Path;
+    use crate::library::lister::{Lister, Status, Summary, LISTER_FILE_NAME};
+    use crate::library::state::{RoutineState, StateStore};
+    use chrono::{Duration, TimeZone, Utc};
+    use git2::Oid;
+    use std::collections::HashMap;
+    use tempfile::tempdir;
 
     // Helper function to create a mock routine TOML file
     fn create_mock_routine(file_path: &Path) {
+        create_mock_routine_titled(file_path, "Notes");
+    }
+
+    // Helper function to create a mock routine TOML file with a specific title
+    fn create_mock_routine_titled(file_path: &Path, title: &str) {
         let routine_content = r#"[base]
-title = "Notes"
+title = "TITLE"
 path = "/path/to/notes"
 
 [base.interval]
@@ -42,36 +50,34 @@ force_push = false
 "#;
 
         let mut file = fs::File::create(file_path).unwrap();
-        file.write_all(routine_content.as_bytes()).unwrap();
+        file.write_all(routine_content.replace("TITLE", title).as_bytes()).unwrap();
     }
 
     #[test]
     fn test_new_creates_lister_with_empty_file() {
-        let config_dir = Configuration::get_dir().unwrap();
+        let dir = tempdir().unwrap();
+        let config_dir = dir.path();
 
         let lister_file = config_dir.join(LISTER_FILE_NAME);
-        let lister = Lister::new();
+        let lister = Lister::at(config_dir);
 
         assert!(lister.is_ok());
         assert!(lister_file.exists());
-
-        remove_dir_all(&config_dir).unwrap();
     }
 
     #[test]
     fn test_initialize_paths_with_toml_files() {
-        let config_dir = Configuration::get_dir().unwrap();
+        let dir = tempdir().unwrap();
+        let config_dir = dir.path();
 
         // Crea un archivo .toml simulado
         let file_1 = config_dir.join("notes.toml");
         create_mock_routine(&file_1);
 
-        let lister = Lister::new().unwrap();
+        let lister = Lister::at(config_dir).unwrap();
 
         assert!(lister.paths.contains_key("Notes"));
         assert_eq!(lister.paths.get("Notes").unwrap(), &dunce::canonicalize(file_1).unwrap());
-
-        remove_dir_all(&config_dir).unwrap();
     }
 
     #[test]
@@ -81,20 +87,26 @@ Notes: ./tasks.toml
 Tasks: ./tasks.toml
 "#;
 
+        let dir = tempdir().unwrap();
+        let config_dir = dir.path();
+        create_mock_routine_titled(&config_dir.join("notes.toml"), "Notes");
+        create_mock_routine_titled(&config_dir.join("tasks.toml"), "Tasks");
+
         let buffer = yaml_data.as_bytes();
-        let parsed_paths = Lister::parse_lister_file(buffer);
+        let parsed_paths = Lister::parse_lister_file(config_dir, buffer);
 
         assert!(parsed_paths.is_ok());
-        let paths = parsed_paths.unwrap();
-        assert_eq!(paths.get("Notes").unwrap(), &dunce::canonicalize(Configuration::get_dir().unwrap().join("notes.toml")).unwrap());
-        assert_eq!(paths.get("Tasks").unwrap(), &dunce::canonicalize(Configuration::get_dir().unwrap().join("tasks.toml")).unwrap());
+        let (paths, _) = parsed_paths.unwrap();
+        assert_eq!(paths.get("Notes").unwrap(), &dunce::canonicalize(config_dir.join("notes.toml")).unwrap());
+        assert_eq!(paths.get("Tasks").unwrap(), &dunce::canonicalize(config_dir.join("tasks.toml")).unwrap());
     }
 
     #[test]
     fn test_add_file_adds_new_entry() {
-        let config_dir = Configuration::get_dir().unwrap();
+        let dir = tempdir().unwrap();
+        let config_dir = dir.path();
 
-        let mut lister = Lister::new().unwrap();
+        let mut lister = Lister::at(config_dir).unwrap();
         let file_path = config_dir.join("new_routine.toml");
         create_mock_routine(&file_path);
 
@@ -103,15 +115,14 @@ Tasks: ./tasks.toml
 
         assert!(lister.paths.contains_key("CustomName"));
         assert_eq!(lister.paths.get("CustomName").unwrap(), &file_path);
-
-        remove_dir_all(&config_dir).unwrap();
     }
 
     #[test]
     fn test_dump_writes_to_file() {
-        let config_dir = Configuration::get_dir().unwrap();
+        let dir = tempdir().unwrap();
+        let config_dir = dir.path();
 
-        let mut lister = Lister::new().unwrap();
+        let mut lister = Lister::at(config_dir).unwrap();
         let file_path = config_dir.join("test_dump.toml");
         create_mock_routine(&file_path);
 
@@ -122,7 +133,271 @@ Tasks: ./tasks.toml
 
         let lister_file_content = fs::read_to_string(lister.file_path).unwrap();
         assert!(lister_file_content.contains("DumpTest"));
+    }
+
+    #[test]
+    fn test_reconcile_reports_changes() {
+        let dir = tempdir().unwrap();
+        let notes = dir.path().join("notes.toml");
+        let moved = dir.path().join("tasks-moved.toml");
+        let outside = dir.path().join("outside.toml");
+        for (path, title) in [(&notes, "Notes"), (&moved, "Tasks"), (&outside, "Outside")] {
+            create_mock_routine_titled(path, title);
+        }
+        let [notes, moved, outside] = [notes, moved, outside].map(|path| dunce::canonicalize(path).unwrap());
+
+        let recorded = HashMap::from([
+            ("Tasks".to_string(), dir.path().join("tasks.toml")),
+            ("Gone".to_string(), dir.path().join("gone.toml")),
+            ("Outside".to_string(), outside.clone()),
+        ]);
+        let found = vec![("Notes".to_string(), notes.clone()), ("Tasks".to_string(), moved.clone())];
+        let (paths, changes) = Lister::reconcile(&recorded, found);
+
+        // Routines registered outside of the scanned directory are kept
+        assert_eq!(paths.len(), 3);
+        assert_eq!(paths["Outside"], outside);
+        assert_eq!(changes.added, vec![("Notes".to_string(), notes)]);
+        assert_eq!(changes.removed, vec![("Gone".to_string(), dir.path().join("gone.toml"))]);
+        assert_eq!(changes.moved, vec![("Tasks".to_string(), dir.path().join("tasks.toml"), moved)]);
+        assert!(changes.duplicates.is_empty());
+        assert!(!changes.is_empty());
+    }
+
+    #[test]
+    fn test_reconcile_names_duplicate_titles() {
+        let dir = tempdir().unwrap();
+        let paths = ["a.toml", "b.toml", "c.toml"].map(|name| {
+            let path = dir.path().join(name);
+            create_mock_routine_titled(&path, "Notes");
+            dunce::canonicalize(path).unwrap()
+        });
+
+        // The routine already listed keeps its name, the others get a numbered one
+        let recorded = HashMap::from([("Notes".to_string(), paths[1].clone())]);
+        let found = paths.iter().map(|path| ("Notes".to_string(), path.clone())).collect();
+        let (listed, changes) = Lister::reconcile(&recorded, found);
+
+        assert_eq!(listed.len(), 3);
+        assert_eq!(listed["Notes"], paths[1]);
+        assert_eq!(listed["Notes-2"], paths[0]);
+        assert_eq!(listed["Notes-3"], paths[2]);
+        assert_eq!(changes.added, vec![("Notes-2".to_string(), paths[0].clone()), ("Notes-3".to_string(), paths[2].clone())]);
+
+        let expected = vec![
+            ("Notes".to_string(), paths[1].clone()),
+            ("Notes-2".to_string(), paths[0].clone()),
+            ("Notes-3".to_string(), paths[2].clone()),
+        ];
+        assert_eq!(changes.duplicates, vec![("Notes".to_string(), expected)]);
+        let report = changes.to_string();
+        assert!(report.contains("! Notes is the title of several routines: Notes ("), "{}", report);
+        assert!(report.contains(&format!("Notes-3 ({})", paths[2].display())), "{}", report);
+
+        // Once listed, nothing changes but the duplicates are still reported
+        let (again, changes) = Lister::reconcile(&listed, paths.iter().map(|path| ("Notes".to_string(), path.clone())).collect());
+        assert_eq!(again, listed);
+        assert!(changes.is_empty());
+        assert!(changes.has_warnings());
+        assert_eq!(changes.duplicates.len(), 1);
+    }
+
+    #[test]
+    fn test_reconcile_keeps_names_when_titles_change() {
+        let dir = tempdir().unwrap();
+        let notes = dir.path().join("notes.toml");
+        create_mock_routine_titled(&notes, "Journal");
+        let notes = dunce::canonicalize(notes).unwrap();
+
+        let recorded = HashMap::from([("Notes".to_string(), notes.clone())]);
+        let (listed, changes) = Lister::reconcile(&recorded, vec![("Journal".to_string(), notes.clone())]);
+
+        assert_eq!(listed, recorded);
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn test_rename_changes_the_name_only() {
+        let dir = tempdir().unwrap();
+        let config_dir = dir.path();
+        create_mock_routine_titled(&config_dir.join("notes.toml"), "Notes");
+        create_mock_routine_titled(&config_dir.join("music.toml"), "Music");
+        let notes = dunce::canonicalize(config_dir.join("notes.toml")).unwrap();
+
+        let mut lister = Lister::at(config_dir).unwrap();
+        assert!(lister.rename(&notes, "Music").unwrap_err().to_string().contains("already named"));
+        assert!(lister.rename(&notes, " ").is_err());
+        assert!(lister.rename(&config_dir.join("gone.toml"), "Gone").is_err());
+
+        lister.rename(&notes, "Journal").unwrap();
+        lister.dump().unwrap();
+
+        // The new name sticks, and the title still finds the routine
+        let lister = Lister::at(config_dir).unwrap();
+        assert!(lister.changes.is_empty());
+        assert_eq!(lister.paths.get("Journal"), Some(&notes));
+        assert!(!lister.paths.contains_key("Notes"));
+        assert_eq!(lister.resolve("Notes").unwrap(), notes);
+    }
+
+    #[test]
+    fn test_resolve_refuses_shared_titles() {
+        let dir = tempdir().unwrap();
+        let config_dir = dir.path();
+        create_mock_routine_titled(&config_dir.join("a.toml"), "Notes");
+        create_mock_routine_titled(&config_dir.join("b.toml"), "Notes");
+
+        let mut lister = Lister::at(config_dir).unwrap();
+        let a = dunce::canonicalize(config_dir.join("a.toml")).unwrap();
+        let b = dunce::canonicalize(config_dir.join("b.toml")).unwrap();
+        assert_eq!(lister.resolve("Notes").unwrap(), a);
+        assert_eq!(lister.resolve("Notes-2").unwrap(), b);
+
+        // Once neither routine is named after the title, the title is ambiguous
+        lister.rename(&a, "Work").unwrap();
+        lister.rename(&b, "Home").unwrap();
+        let error = lister.resolve("Notes").unwrap_err().to_string();
+        assert!(error.contains("Several routines are titled \"Notes\""), "{}", error);
+        assert!(error.contains("\"Home\", \"Work\""), "{}", error);
+
+        // Adding a listed file again keeps its name
+        assert_eq!(lister.add_file(&a, None), "Work");
+        assert_eq!(lister.paths.len(), 2);
+    }
+
+    #[test]
+    fn test_new_prunes_deleted_routines_until_dumped() {
+        let dir = tempdir().unwrap();
+        let config_dir = dir.path();
+        let notes = config_dir.join("notes.toml");
+        create_mock_routine(&notes);
+
+        // The first load finds the routine, a comment-only lister file recording nothing
+        let mut lister = Lister::at(config_dir).unwrap();
+        assert_eq!(lister.changes.added.len(), 1);
+        lister.dump().unwrap();
+        assert!(Lister::at(config_dir).unwrap().changes.is_empty());
+
+        fs::remove_file(&notes).unwrap();
+        let mut lister = Lister::at(config_dir).unwrap();
+        assert!(lister.paths.is_empty());
+        assert_eq!(lister.changes.removed.len(), 1);
+        assert!(lister.changes.to_string().starts_with("- Notes: "));
+
+        lister.dump().unwrap();
+        assert!(Lister::at(config_dir).unwrap().changes.is_empty());
+    }
+
+    #[test]
+    fn test_summary_reports_health() {
+        let dir = tempdir().unwrap();
+        let store = StateStore::at(dir.path().join("state"));
+        let routine_path = dir.path().join("notes.toml");
+        create_mock_routine(&routine_path);
+        let now = Utc.with_ymd_and_hms(2024, 12, 15, 12, 0, 0).unwrap();
+
+        // Renamed routines show both their name and their title
+        let summary = Summary::new("Journal", &routine_path, &store, now);
+        let table = tabled::Table::new([&summary]).to_string();
+        assert!(table.contains("name") && table.contains("title"), "{}", table);
+        assert!(table.contains("Journal") && table.contains("Notes"), "{}", table);
+
+        // Never synced, so due right away
+        let summary = Summary::new("Notes", &routine_path, &store, now);
+        assert_eq!(summary.status, Status::Due);
+        assert_eq!(summary.next_due, Some(now));
+        assert!(summary.due && !summary.is_failing());
+        assert_eq!(summary.remote, "https://github.com/exam/ple");
+        assert_eq!(summary.path, "/path/to/notes");
+        assert_eq!(summary.file, routine_path.display().to_string());
+
+        let mut state = RoutineState::default();
+        state.record(now - Duration::hours(1), Ok(Oid::zero()));
+        store.save(&routine_path, &state).unwrap();
+        let summary = Summary::new("Notes", &routine_path, &store, now);
+        assert_eq!(summary.status, Status::Ok);
+        assert_eq!(summary.next_due, Some(now + Duration::hours(23)));
+
+        let offline: Box<dyn std::error::Error> = "offline".into();
+        state.record(now, Err(&*offline));
+        store.save(&routine_path, &state).unwrap();
+        let summary = Summary::new("Notes", &routine_path, &store, now);
+        assert_eq!(summary.status, Status::Failing);
+        assert_eq!(summary.last_error.as_deref(), Some("offline"));
+        assert!(summary.is_failing() && !summary.due);
+
+        // Routines whose file is gone still show up, as failing
+        let summary = Summary::new("Gone", &dir.path().join("gone.toml"), &store, now);
+        assert_eq!(summary.status, Status::Unreadable);
+        assert!(summary.path.is_empty());
+        assert_eq!(summary.file, dir.path().join("gone.toml").display().to_string());
+        assert!(summary.is_failing());
+    }
+
+    #[test]
+    fn test_select_resolves_titles_and_paths() {
+        let dir = tempdir().unwrap();
+        let config_dir = dir.path();
+        create_mock_routine_titled(&config_dir.join("notes.toml"), "Notes");
+        create_mock_routine_titled(&config_dir.join("music.toml"), "Music");
+        let notes = dunce::canonicalize(config_dir.join("notes.toml")).unwrap();
+        let lister = Lister::at(config_dir).unwrap();
+
+        assert_eq!(lister.select(Some("Notes"), false).unwrap(), vec![notes.clone()]);
+        let path = config_dir.join("notes.toml");
+        assert_eq!(lister.resolve(&path.display().to_string()).unwrap(), path);
+
+        // All routines, sorted by title
+        let music = dunce::canonicalize(config_dir.join("music.toml")).unwrap();
+        assert_eq!(lister.select(None, true).unwrap(), vec![music, notes]);
+        assert!(lister.select(None, false).is_err());
+
+        // Unknown titles come with suggestions
+        let error = lister.resolve("notse").unwrap_err().to_string();
+        assert!(error.contains("did you mean \"Notes\""), "{}", error);
+        let error = lister.resolve("note").unwrap_err().to_string();
+        assert!(error.contains("did you mean \"Notes\"?"), "{}", error);
+        let error = lister.resolve("Podcasts").unwrap_err().to_string();
+        assert!(!error.contains("did you mean"), "{}", error);
+    }
+
+    #[test]
+    fn test_broken_routines_are_listed_as_broken() {
+        let dir = tempdir().unwrap();
+        let config_dir = dir.path();
+        create_mock_routine_titled(&config_dir.join("notes.toml"), "Notes");
+        fs::write(config_dir.join("broken.toml"), "[base]\ntitle = \"Broken\"\n").unwrap();
+        let notes = dunce::canonicalize(config_dir.join("notes.toml")).unwrap();
+        let broken = dunce::canonicalize(config_dir.join("broken.toml")).unwrap();
+
+        // The broken file is registered under its file name and reported, the others still load
+        let mut lister = Lister::at(config_dir).unwrap();
+        assert_eq!(lister.paths.get("Notes"), Some(&notes));
+        assert_eq!(lister.paths.get("broken"), Some(&broken));
+        assert_eq!(lister.changes.broken.len(), 1);
+        assert_eq!(lister.changes.broken[0].0, "broken");
+        assert!(lister.changes.broken[0].2.contains("Invalid routine file"));
+        assert!(lister.changes.to_string().contains("! broken is broken: Invalid routine file"), "{}", lister.changes);
+        assert_eq!(lister.select(None, true).unwrap(), vec![notes.clone(), broken.clone()]);
+
+        let store = StateStore::at(config_dir.join("state"));
+        let now = Utc.with_ymd_and_hms(2024, 12, 15, 12, 0, 0).unwrap();
+        let summaries = lister.summaries(&store, now);
+        assert_eq!(summaries[0].status, Status::Due);
+        assert_eq!(summaries[1].status, Status::Broken);
+        assert!(summaries[1].is_failing());
+        assert!(summaries[1].last_error.as_deref().unwrap().contains("Invalid routine file"));
+
+        // Once registered, it is still reported until it is fixed
+        lister.dump().unwrap();
+        let lister = Lister::at(config_dir).unwrap();
+        assert!(lister.changes.is_empty());
+        assert_eq!(lister.changes.broken.len(), 1);
 
-        remove_dir_all(&config_dir).unwrap();
+        create_mock_routine_titled(&broken, "Fixed");
+        let lister = Lister::at(config_dir).unwrap();
+        assert!(lister.changes.broken.is_empty());
+        assert!(!lister.changes.has_warnings());
+        assert_eq!(lister.paths.get("broken"), Some(&broken));
     }
 }
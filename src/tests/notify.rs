@@ -0,0 +1,266 @@
+#[cfg(test)]
+mod notify_tests {
+    use crate::library::notify::{LogFormat, NotifyBackend, NotifyTarget};
+    use crate::library::routine::Routine;
+    use git2::Repository;
+    use std::fs;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::path::Path;
+    use std::sync::mpsc::{self, Receiver};
+    use std::thread;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    /// A request received by the stand-in server
+    struct Request {
+        path: String,
+        authorization: Option<String>,
+        body: serde_json::Value,
+    }
+
+    // Helper function to start a stand-in ntfy server, returning its URL and received requests
+    fn start_server() -> (String, Receiver<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut reader = BufReader::new(stream.unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+
+                let mut length = 0;
+                let mut authorization = None;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let Some((name, value)) = line.trim_end().split_once(": ") else { break };
+                    match name.to_lowercase().as_str() {
+                        "content-length" => length = value.parse().unwrap(),
+                        "authorization" => authorization = Some(value.to_string()),
+                        _ => {},
+                    }
+                }
+
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                reader.get_mut().write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}").unwrap();
+
+                let path = request_line.split_whitespace().nth(1).unwrap_or_default().to_string();
+                let body = serde_json::from_slice(&body).unwrap();
+                let _ = sender.send(Request { path, authorization, body });
+            }
+        });
+
+        (url, receiver)
+    }
+
+    // Helper function to create a routine notifying the server at `server`
+    fn create_routine(path: &Path, remote: &Path, server: &str) -> Routine {
+        let mut routine = Routine::new("Notes".to_string(), path.display().to_string());
+        routine.git.remote = remote.display().to_string();
+        routine.ntfy.ntfy_topic = "sync-updates".to_string();
+        routine.ntfy.notification_on_success = true;
+        routine.ntfy.notification_on_failure = true;
+        routine.ntfy.server = Some(format!("{}/", server));
+        routine
+    }
+
+    fn receive(receiver: &Receiver<Request>) -> Request {
+        receiver.recv_timeout(Duration::from_secs(10)).expect("No notification was sent")
+    }
+
+    #[test]
+    fn test_notify_throw_and_catch_success() {
+        let (server, requests) = start_server();
+        let remote_dir = tempdir().unwrap();
+        Repository::init_bare(remote_dir.path()).unwrap();
+
+        let work_dir = tempdir().unwrap();
+        fs::write(work_dir.path().join("a.txt"), "a").unwrap();
+        fs::write(work_dir.path().join("b.txt"), "b").unwrap();
+        let mut routine = create_routine(work_dir.path(), remote_dir.path(), &server);
+        routine.ntfy.priority = Some(4);
+        routine.ntfy.tags = vec!["floppy_disk".to_string()];
+        routine.ntfy.token = Some("tk_secret".into());
+        routine.throw(&None).unwrap();
+
+        let commit = Repository::open(work_dir.path()).unwrap().head().unwrap().target().unwrap();
+        let request = receive(&requests);
+        assert_eq!(request.path, "/");
+        assert_eq!(request.authorization.as_deref(), Some("Bearer tk_secret"));
+        assert_eq!(request.body["topic"], "sync-updates");
+        assert_eq!(request.body["title"], "Notes: throw succeeded");
+        assert_eq!(request.body["priority"], 4);
+        assert_eq!(request.body["tags"], serde_json::json!(["floppy_disk"]));
+        let message = request.body["message"].as_str().unwrap();
+        assert!(message.contains(&commit.to_string()[..8]));
+        assert!(message.contains("2 added, 0 modified, 0 deleted"));
+
+        let target_dir = tempdir().unwrap();
+        create_routine(target_dir.path(), remote_dir.path(), &server).catch().unwrap();
+
+        let request = receive(&requests);
+        assert_eq!(request.authorization, None);
+        assert_eq!(request.body["title"], "Notes: catch succeeded");
+        assert!(request.body.get("priority").is_none());
+        assert!(request.body["message"].as_str().unwrap().contains("2 added"));
+    }
+
+    #[test]
+    fn test_notify_failure_only_when_asked() {
+        let (server, requests) = start_server();
+        let work_dir = tempdir().unwrap();
+        let missing_remote = work_dir.path().join("missing-remote");
+        fs::write(work_dir.path().join("a.txt"), "a").unwrap();
+
+        let mut routine = create_routine(work_dir.path(), &missing_remote, &server);
+        routine.ntfy.notification_on_success = false;
+        let error = routine.throw(&None).unwrap_err();
+
+        let request = receive(&requests);
+        assert_eq!(request.body["title"], "Notes: throw failed");
+        assert_eq!(request.body["message"], error.to_string());
+
+        // Successful syncs are not notified when only failures are asked for
+        fs::remove_file(work_dir.path().join("a.txt")).unwrap();
+        routine.git.remote = {
+            let remote_dir = work_dir.path().join("remote");
+            Repository::init_bare(&remote_dir).unwrap();
+            remote_dir.display().to_string()
+        };
+        routine.throw(&None).unwrap();
+        assert!(requests.recv_timeout(Duration::from_millis(500)).is_err());
+    }
+
+    #[test]
+    fn test_notify_errors_do_not_fail_syncs() {
+        let remote_dir = tempdir().unwrap();
+        Repository::init_bare(remote_dir.path()).unwrap();
+        let work_dir = tempdir().unwrap();
+        fs::write(work_dir.path().join("a.txt"), "a").unwrap();
+
+        // Nothing listens on this port once the listener is dropped
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let routine = create_routine(work_dir.path(), remote_dir.path(), &format!("http://{}", closed));
+
+        assert!(routine.throw(&None).is_ok());
+    }
+
+    #[test]
+    fn test_parse_notify_entries() {
+        let routine = Routine::new("Notes".to_string(), "/tmp".to_string());
+        let content = format!(
+            "{}{}",
+            toml::to_string(&routine).unwrap(),
+            r#"
+            [[notify]]
+            kind = "webhook"
+            url = "https://hooks.example.com/T0/B0/secret"
+            headers = { Authorization = "Bearer secret" }
+            on_success = false
+
+            [[notify]]
+            kind = "desktop"
+
+            [[notify]]
+            kind = "command"
+            command = "notify-team --channel ops"
+
+            [[notify]]
+            kind = "log"
+            path = "/var/log/act.mbox"
+            format = "mbox"
+            "#
+        );
+        let routine: Routine = toml::from_str(&content).unwrap();
+
+        assert_eq!(routine.notifiers.len(), 4);
+        assert!(!routine.notifiers[0].on_success && routine.notifiers[0].on_failure);
+        assert_eq!(routine.notifiers[1].backend, NotifyBackend::Desktop);
+        assert!(matches!(&routine.notifiers[3].backend, NotifyBackend::Log { format: LogFormat::Mbox, .. }));
+
+        // Webhook URLs carry credentials and are masked like passwords
+        assert_eq!(routine.notifiers[0].describe(false), "webhook ******** on failure");
+        assert!(routine.notifiers[0].describe(true).contains("/T0/B0/secret"));
+
+        // Entries survive being written back
+        let written = toml::to_string(&routine).unwrap();
+        assert_eq!(toml::from_str::<Routine>(&written).unwrap().notifiers, routine.notifiers);
+
+        assert!(toml::from_str::<Routine>(&content.replace("\"desktop\"", "\"pigeon\"")).is_err());
+    }
+
+    #[test]
+    fn test_notify_webhook() {
+        let (server, requests) = start_server();
+        let remote_dir = tempdir().unwrap();
+        Repository::init_bare(remote_dir.path()).unwrap();
+        let work_dir = tempdir().unwrap();
+        fs::write(work_dir.path().join("a.txt"), "a").unwrap();
+
+        let mut routine = Routine::new("Notes".to_string(), work_dir.path().display().to_string());
+        routine.git.remote = remote_dir.path().display().to_string();
+        routine.notifiers.push(NotifyTarget {
+            backend: NotifyBackend::Webhook {
+                url: format!("{}/hooks/sync", server).into(),
+                headers: [("Authorization".to_string(), "Token abc".into())].into(),
+            },
+            on_success: true,
+            on_failure: true,
+        });
+        routine.throw(&None).unwrap();
+
+        let request = receive(&requests);
+        assert_eq!(request.path, "/hooks/sync");
+        assert_eq!(request.authorization.as_deref(), Some("Token abc"));
+        assert_eq!(request.body["routine"], "Notes");
+        assert_eq!(request.body["action"], "throw");
+        assert_eq!(request.body["status"], "success");
+        assert_eq!(request.body["added"], 1);
+        assert!(request.body["error"].is_null());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_notify_routes_to_command_and_logs() {
+        let work_dir = tempdir().unwrap();
+        let logs_dir = tempdir().unwrap();
+        fs::write(work_dir.path().join("a.txt"), "a").unwrap();
+
+        let log = |name: &str, format, on_success, on_failure| NotifyTarget {
+            backend: NotifyBackend::Log { path: logs_dir.path().join(name), format },
+            on_success,
+            on_failure,
+        };
+        let env_file = logs_dir.path().join("env.txt");
+        let mut routine = Routine::new("Notes".to_string(), work_dir.path().display().to_string());
+        routine.git.remote = work_dir.path().join("missing-remote").display().to_string();
+        routine.notifiers = vec![
+            log("all.log", LogFormat::Text, true, true),
+            log("failures.mbox", LogFormat::Mbox, false, true),
+            log("successes.log", LogFormat::Text, true, false),
+            NotifyTarget {
+                backend: NotifyBackend::Command {
+                    command: format!(
+                        "sh -c 'echo \"$ACT_STATUS $ACT_ACTION $ACT_ROUTINE\" > {}'",
+                        env_file.display()
+                    ),
+                },
+                on_success: true,
+                on_failure: true,
+            },
+        ];
+        assert!(routine.throw(&None).is_err());
+
+        let all = fs::read_to_string(logs_dir.path().join("all.log")).unwrap();
+        assert!(all.contains("FAILURE Notes: throw failed"));
+        let mbox = fs::read_to_string(logs_dir.path().join("failures.mbox")).unwrap();
+        assert!(mbox.starts_with("From act-iv@localhost "));
+        assert!(mbox.contains("\nSubject: Notes: throw failed\n"));
+        assert!(!logs_dir.path().join("successes.log").exists());
+        assert_eq!(fs::read_to_string(&env_file).unwrap(), "failure throw Notes\n");
+    }
+}
@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod press_tests {
+    use crate::library::press::{pack, unpack, Compression};
+    use crate::library::routine::Routine;
+    use git2::Repository;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    // Helper function to create compressible content of the given size
+    fn log_lines(size: usize) -> Vec<u8> {
+        (0..).flat_map(|line| format!("{} INFO request served\n", line).into_bytes()).take(size).collect()
+    }
+
+    #[test]
+    fn test_parse_compression() {
+        for method in ["nil", "gz", "xz", "bz2", "lzma2", "zstd -q -c"] {
+            assert_eq!(method.parse::<Compression>().unwrap().to_string(), method);
+        }
+
+        assert_eq!("zstd -q -c".parse::<Compression>().unwrap(), Compression::Command("zstd -q -c".to_string()));
+        assert!("".parse::<Compression>().is_err());
+    }
+
+    #[test]
+    fn test_pack_round_trips() {
+        let content = log_lines(64 * 1024);
+
+        for method in ["gz", "xz", "bz2", "lzma2"] {
+            let packed = pack(&content, &method.parse().unwrap(), 1024).unwrap();
+
+            assert!(packed.len() < content.len() / 4, "{} did not compress", method);
+            assert_eq!(unpack(&packed, None).unwrap(), content);
+        }
+    }
+
+    #[test]
+    fn test_pack_leaves_small_and_incompressible_files() {
+        let content = log_lines(512);
+        assert_eq!(pack(&content, &Compression::Gz, 1024).unwrap(), content);
+        assert_eq!(pack(&content, &Compression::Nil, 0).unwrap(), content);
+
+        // Content that looks like a compressed file is still read back as it was
+        let lookalike = [b"ACT-IV\x02\x01".as_slice(), &content].concat();
+        let packed = pack(&lookalike, &Compression::Nil, 0).unwrap();
+        assert_ne!(packed, lookalike);
+        assert_eq!(unpack(&packed, None).unwrap(), lookalike);
+
+        // Compression that does not help is not used
+        let random = (0..4096u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect::<Vec<_>>();
+        let packed = pack(&random, &Compression::Gz, 0).unwrap();
+        assert!(packed.len() <= random.len() + 8);
+        assert_eq!(unpack(&packed, None).unwrap(), random);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_pack_with_command() {
+        let content = log_lines(64 * 1024);
+        let compression = "gzip -c -n".parse().unwrap();
+        let packed = pack(&content, &compression, 0).unwrap();
+
+        assert!(packed.len() < content.len() / 4);
+        assert_eq!(unpack(&packed, Some("gzip -d -c")).unwrap(), content);
+
+        // Reading it back requires the matching command
+        assert!(unpack(&packed, None).is_err());
+        assert!(pack(&content, &"false".parse().unwrap(), 0).is_err());
+    }
+
+    #[test]
+    fn test_compressed_throw_and_catch() {
+        let remote_dir = tempdir().unwrap();
+        Repository::init_bare(remote_dir.path()).unwrap();
+
+        let work_dir = tempdir().unwrap();
+        let large = log_lines(256 * 1024);
+        fs::write(work_dir.path().join("server.log"), &large).unwrap();
+        fs::write(work_dir.path().join("notes.txt"), "small").unwrap();
+
+        let mut thrower = Routine::new("Logs".to_string(), work_dir.path().display().to_string());
+        thrower.git.remote = remote_dir.path().display().to_string();
+        thrower.press.compression = "xz".parse().unwrap();
+        thrower.press.threshold = Some(64 * 1024);
+        thrower.throw(&None).unwrap();
+
+        // Only files above the threshold are compressed in the remote
+        let remote = Repository::open_bare(remote_dir.path()).unwrap();
+        let tree = remote.revparse_single("refs/heads/main").unwrap().peel_to_tree().unwrap();
+        let blob_size = |name: &str| {
+            remote.find_blob(tree.get_path(Path::new(name)).unwrap().id()).unwrap().size()
+        };
+        assert!(blob_size("server.log") < large.len() / 4);
+        assert_eq!(blob_size("notes.txt"), 5);
+
+        // Catching decompresses, whatever the catching routine's own compression
+        let target_dir = tempdir().unwrap();
+        let mut catcher = Routine::new("Logs".to_string(), target_dir.path().display().to_string());
+        catcher.git.remote = remote_dir.path().display().to_string();
+        catcher.catch().unwrap();
+
+        assert_eq!(fs::read(target_dir.path().join("server.log")).unwrap(), large);
+        assert_eq!(fs::read_to_string(target_dir.path().join("notes.txt")).unwrap(), "small");
+    }
+}
@@ -2,12 +2,51 @@
 mod routine_tests {
 
     // Importing the necessary code
-    use crate::library::routine::Routine;
+    use crate::library::routine::{Routine, StageOutcome};
+    use crate::library::state::RoutineState;
+    use git2::Repository;
     use std::fs::File;
     use std::io::Write;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
     use tempfile::tempdir;
 
+    // Helper function to create a routine syncing `path` to `remote`
+    fn create_routine(path: &Path, remote: &Path, force_push: bool) -> Routine {
+        let toml_content = format!(
+            r#"
+            [base]
+            title = "Notes"
+            path = "{}/"
+            interval = {{ commit_interval = "1d", sync_method = "thorough", timestamp = true, time_zone = "UTC" }}
+
+            [crypt]
+            encrypt = false
+
+            [press]
+            compression = "nil"
+
+            [advanced]
+            exclude = []
+            sync_on_startup = false
+
+            [ntfy]
+            ntfy_topic = "sync-updates"
+            notification_on_success = false
+            notification_on_failure = false
+
+            [git]
+            remote = "{}"
+            branch = "main"
+            force_push = {}
+        "#,
+            path.display(),
+            remote.display(),
+            force_push
+        );
+
+        toml::from_str(&toml_content).unwrap()
+    }
+
     #[test]
     fn test_routine_read_success() {
         // Create a temporary directory to write the routine file
@@ -63,4 +102,291 @@ mod routine_tests {
         // Assert that the routine read failed
         assert!(routine.is_err());
     }
+
+    #[test]
+    fn test_example_routine_is_valid() {
+        let example = include_str!("../../examples/routine.toml");
+        let routine: Routine = toml::from_str(example).unwrap();
+        assert_eq!(routine.base.title, "Notes");
+
+        // Every setting, commented out or not, starts its own line rather than trailing a comment
+        for line in example.lines() {
+            let setting = line.strip_prefix("# ").unwrap_or(line);
+            if let Some((_, comment)) = setting.split_once('#') {
+                assert!(!comment.contains(" = "), "{}", line);
+            }
+        }
+    }
+
+    #[test]
+    fn test_routine_read_invalid_file() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("broken.toml");
+        std::fs::write(&file_path, "[base]\ntitle = \"Notes\"\n").unwrap();
+
+        // A file that is not a valid routine is an error rather than a panic
+        let error = Routine::read(&file_path).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("Invalid routine file"), "{}", error);
+        assert!(error.to_string().contains("broken.toml"), "{}", error);
+    }
+
+    #[test]
+    fn test_routine_throw_pushes_to_remote() {
+        // Create a directory to sync and a bare repository acting as the remote
+        let work_dir = tempdir().unwrap();
+        let remote_dir = tempdir().unwrap();
+        Repository::init_bare(remote_dir.path()).unwrap();
+        std::fs::write(work_dir.path().join("notes.txt"), "some notes").unwrap();
+
+        let routine = create_routine(work_dir.path(), remote_dir.path(), false);
+        routine.throw(&None).unwrap();
+
+        // The remote branch should point to the commit created locally
+        let local = Repository::open(work_dir.path()).unwrap();
+        let remote = Repository::open_bare(remote_dir.path()).unwrap();
+        let local_head = local.head().unwrap().peel_to_commit().unwrap().id();
+        let remote_head = remote.refname_to_id("refs/heads/main").unwrap();
+
+        assert_eq!(local_head, remote_head);
+    }
+
+    #[test]
+    fn test_routine_throw_honours_force_push() {
+        let remote_dir = tempdir().unwrap();
+        Repository::init_bare(remote_dir.path()).unwrap();
+
+        // Push a first history to the remote
+        let first_dir = tempdir().unwrap();
+        std::fs::write(first_dir.path().join("notes.txt"), "first").unwrap();
+        create_routine(first_dir.path(), remote_dir.path(), false).throw(&None).unwrap();
+
+        // An unrelated history must be rejected unless force pushing
+        let second_dir = tempdir().unwrap();
+        std::fs::write(second_dir.path().join("notes.txt"), "second").unwrap();
+        let mut routine = create_routine(second_dir.path(), remote_dir.path(), false);
+
+        assert!(routine.throw(&None).is_err());
+
+        routine.git.force_push = true;
+        routine.push().unwrap();
+
+        let local = Repository::open(second_dir.path()).unwrap();
+        let remote = Repository::open_bare(remote_dir.path()).unwrap();
+        let local_head = local.head().unwrap().peel_to_commit().unwrap().id();
+
+        assert_eq!(remote.refname_to_id("refs/heads/main").unwrap(), local_head);
+    }
+
+    #[test]
+    fn test_routine_catch_clones_and_fast_forwards() {
+        let remote_dir = tempdir().unwrap();
+        Repository::init_bare(remote_dir.path()).unwrap();
+
+        let work_dir = tempdir().unwrap();
+        std::fs::write(work_dir.path().join("notes.txt"), "first").unwrap();
+        let thrower = create_routine(work_dir.path(), remote_dir.path(), false);
+        thrower.throw(&None).unwrap();
+
+        // Catching into a missing directory clones the remote branch
+        let target_dir = tempdir().unwrap();
+        let target_path = target_dir.path().join("notes");
+        let catcher = create_routine(&target_path, remote_dir.path(), false);
+        catcher.catch().unwrap();
+
+        assert_eq!(std::fs::read_to_string(target_path.join("notes.txt")).unwrap(), "first");
+
+        // Catching again after a new throw fast-forwards the existing clone
+        std::fs::write(work_dir.path().join("notes.txt"), "second").unwrap();
+        thrower.throw(&None).unwrap();
+        catcher.catch().unwrap();
+
+        assert_eq!(std::fs::read_to_string(target_path.join("notes.txt")).unwrap(), "second");
+    }
+
+    #[test]
+    fn test_routine_catch_refuses_to_overwrite_local_changes() {
+        let remote_dir = tempdir().unwrap();
+        Repository::init_bare(remote_dir.path()).unwrap();
+
+        let work_dir = tempdir().unwrap();
+        std::fs::write(work_dir.path().join("notes.txt"), "first").unwrap();
+        let thrower = create_routine(work_dir.path(), remote_dir.path(), false);
+        thrower.throw(&None).unwrap();
+
+        let target_dir = tempdir().unwrap();
+        let catcher = create_routine(target_dir.path(), remote_dir.path(), false);
+        catcher.catch().unwrap();
+
+        // Modify the file on both sides, only committing the change on the throwing side
+        std::fs::write(work_dir.path().join("notes.txt"), "second").unwrap();
+        thrower.throw(&None).unwrap();
+        std::fs::write(target_dir.path().join("notes.txt"), "local").unwrap();
+
+        let error = catcher.catch().unwrap_err();
+
+        assert!(error.to_string().contains("notes.txt"));
+        assert_eq!(std::fs::read_to_string(target_dir.path().join("notes.txt")).unwrap(), "local");
+    }
+
+    // Helper function to stage `content` with a timestamp message
+    fn stage_notes(routine: &Routine, content: &str, timestamp: &str) {
+        std::fs::write(Path::new(&routine.base.path).join("notes.txt"), content).unwrap();
+        routine.stage(&Some(timestamp.to_string())).unwrap();
+    }
+
+    #[test]
+    fn test_routine_wind_by_count_creates_new_commit() {
+        let work_dir = tempdir().unwrap();
+        let routine = create_routine(work_dir.path(), work_dir.path(), false);
+        stage_notes(&routine, "first", "2024-12-13 10:00:00");
+        stage_notes(&routine, "second", "2024-12-14 10:00:00");
+        stage_notes(&routine, "ruined", "2024-12-15 10:00:00");
+
+        let before = routine.history().unwrap();
+        routine.wind("1-ago").unwrap();
+        let after = routine.history().unwrap();
+
+        // The rewind is a new commit on top of the existing history
+        assert_eq!(after.len(), before.len() + 1);
+        assert_eq!(after[1].id, before[0].id);
+        assert!(after[0].message.starts_with("ACT-IV Wind"));
+        assert_eq!(std::fs::read_to_string(work_dir.path().join("notes.txt")).unwrap(), "second");
+
+        // Winding back over the rewind undoes it
+        routine.wind("1-ago").unwrap();
+
+        assert_eq!(std::fs::read_to_string(work_dir.path().join("notes.txt")).unwrap(), "ruined");
+    }
+
+    #[test]
+    fn test_routine_wind_by_date_and_commit() {
+        let work_dir = tempdir().unwrap();
+        let routine = create_routine(work_dir.path(), work_dir.path(), false);
+        stage_notes(&routine, "first", "2024-12-13 10:00:00");
+        stage_notes(&routine, "second", "2024-12-14 10:00:00");
+        stage_notes(&routine, "third", "2024-12-15 10:00:00");
+
+        // The latest snapshot at or before the date is selected
+        routine.wind("2024-12-14 23:00:00").unwrap();
+
+        assert_eq!(std::fs::read_to_string(work_dir.path().join("notes.txt")).unwrap(), "second");
+
+        let first = routine.history().unwrap().into_iter().find(|s| s.time == "2024-12-13 10:00:00");
+        routine.wind(&first.unwrap().id).unwrap();
+
+        assert_eq!(std::fs::read_to_string(work_dir.path().join("notes.txt")).unwrap(), "first");
+        assert!(routine.wind("2020-01-01").is_err());
+    }
+
+    #[test]
+    fn test_routine_new_round_trips() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("ACT-Notes.toml");
+
+        // A new routine must be a complete routine file once written
+        Routine::new("Notes".to_string(), "/path/to/notes".to_string()).write(&file_path).unwrap();
+        let routine = Routine::read(&file_path).unwrap();
+
+        assert_eq!(routine.base.title, "Notes");
+        assert_eq!(routine.base.path, "/path/to/notes");
+        assert_eq!(routine.git.branch, "main");
+        assert!(!routine.crypt.encrypt);
+    }
+
+    #[test]
+    fn test_routine_masks_secrets() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("ACT-Notes.toml");
+        let mut routine = Routine::new("Notes".to_string(), "/path/to/notes".to_string());
+        routine.crypt.password = Some("secretpassword".into());
+
+        let password_row = |reveal| {
+            routine.get_data(&RoutineState::default(), reveal).into_iter().map(|row| format!("{:?}", row)).find(|row| {
+                row.contains("\"Password\"")
+            })
+        };
+        assert!(!password_row(false).unwrap().contains("secretpassword"));
+        assert!(password_row(true).unwrap().contains("secretpassword"));
+        assert!(!format!("{:?}", routine).contains("secretpassword"));
+
+        // The routine file still holds the actual value
+        routine.write(&file_path).unwrap();
+        let routine = Routine::read(&file_path).unwrap();
+        assert_eq!(routine.crypt.password.unwrap().expose(), "secretpassword");
+    }
+
+    // Helper function to list the paths committed at HEAD
+    fn committed_paths(path: &Path) -> Vec<String> {
+        let repo = Repository::open(path).unwrap();
+        let tree = repo.head().unwrap().peel_to_tree().unwrap();
+        let mut paths = Vec::new();
+        tree.walk(git2::TreeWalkMode::PreOrder, |root, entry| {
+            if entry.kind() == Some(git2::ObjectType::Blob) {
+                paths.push(format!("{}{}", root, entry.name().unwrap()));
+            }
+            git2::TreeWalkResult::Ok
+        })
+        .unwrap();
+        paths
+    }
+
+    #[test]
+    fn test_routine_stage_tracks_additions_renames_and_deletions() {
+        // The routine path deliberately has no trailing slash
+        let work_dir = tempdir().unwrap();
+        let routine = Routine::new("Notes".to_string(), work_dir.path().display().to_string());
+        std::fs::create_dir(work_dir.path().join("sub dir")).unwrap();
+        std::fs::write(work_dir.path().join("my notes.txt"), "notes").unwrap();
+        std::fs::write(work_dir.path().join("sub dir/todo list.txt"), "todo").unwrap();
+        std::fs::write(work_dir.path().join("old.txt"), "old").unwrap();
+
+        routine.stage(&None).unwrap();
+
+        assert_eq!(committed_paths(work_dir.path()), vec!["my notes.txt", "old.txt", "sub dir/todo list.txt"]);
+
+        // Renamed and deleted files must be reflected in the next commit
+        std::fs::rename(work_dir.path().join("old.txt"), work_dir.path().join("new.txt")).unwrap();
+        std::fs::remove_file(work_dir.path().join("sub dir/todo list.txt")).unwrap();
+        routine.stage(&None).unwrap();
+
+        assert_eq!(committed_paths(work_dir.path()), vec!["my notes.txt", "new.txt"]);
+    }
+
+    #[test]
+    fn test_routine_stage_skips_unchanged_tree() {
+        let work_dir = tempdir().unwrap();
+        let routine = Routine::new("Notes".to_string(), work_dir.path().display().to_string());
+        std::fs::write(work_dir.path().join("notes.txt"), "notes").unwrap();
+
+        assert!(matches!(routine.stage(&None).unwrap(), StageOutcome::Committed(_)));
+        let history = routine.history().unwrap();
+
+        assert_eq!(routine.stage(&None).unwrap(), StageOutcome::Unchanged);
+        assert_eq!(routine.history().unwrap().len(), history.len());
+    }
+
+    #[test]
+    fn test_routine_throw_skips_push_only_when_remote_is_up_to_date() {
+        let work_dir = tempdir().unwrap();
+        let remote_dir = tempdir().unwrap();
+        std::fs::write(work_dir.path().join("notes.txt"), "notes").unwrap();
+        let routine = create_routine(work_dir.path(), remote_dir.path(), false);
+
+        // The first push fails as there is no remote repository yet
+        assert!(routine.throw(&None).is_err());
+
+        // Nothing changed since, but the commit still has to reach the remote
+        Repository::init_bare(remote_dir.path()).unwrap();
+        assert_eq!(routine.throw(&None).unwrap().0, StageOutcome::Unchanged);
+
+        let local = Repository::open(work_dir.path()).unwrap();
+        let remote = Repository::open_bare(remote_dir.path()).unwrap();
+        let local_head = local.head().unwrap().peel_to_commit().unwrap().id();
+        assert_eq!(remote.refname_to_id("refs/heads/main").unwrap(), local_head);
+
+        // Once pushed, an unchanged throw does not contact the remote at all
+        std::fs::remove_dir_all(remote_dir.path()).unwrap();
+        assert_eq!(routine.throw(&None).unwrap().0, StageOutcome::Unchanged);
+    }
 }
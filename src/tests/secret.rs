@@ -0,0 +1,68 @@
+#[cfg(all(test, unix))]
+mod secret_tests {
+    use crate::library::routine::Routine;
+    use crate::library::secret::{eval_password, masked, Secret, MASK};
+    use std::time::{Duration, Instant};
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn test_eval_password_trims_output() {
+        let password = eval_password("printf '  secret\\n\\n'", false, TIMEOUT).unwrap();
+        assert_eq!(password.as_str(), "secret");
+    }
+
+    #[test]
+    fn test_eval_password_runs_without_shell_by_default() {
+        // Without a shell, the pipe is just another argument to echo
+        let password = eval_password("echo secret | tr s S", false, TIMEOUT).unwrap();
+        assert_eq!(password.as_str(), "secret | tr s S");
+
+        let password = eval_password("echo secret | tr s S", true, TIMEOUT).unwrap();
+        assert_eq!(password.as_str(), "Secret");
+    }
+
+    #[test]
+    fn test_eval_password_reports_failures() {
+        let error = eval_password("sh -c 'echo locked >&2; exit 3'", false, TIMEOUT).unwrap_err();
+        assert!(error.to_string().contains("locked"));
+
+        assert!(eval_password("true", false, TIMEOUT).is_err());
+        assert!(eval_password("/nonexistent/password-command", false, TIMEOUT).is_err());
+        assert!(eval_password("echo 'unterminated", false, TIMEOUT).is_err());
+    }
+
+    #[test]
+    fn test_eval_password_times_out() {
+        let start = Instant::now();
+        let error = eval_password("sleep 10", false, Duration::from_millis(200)).unwrap_err();
+
+        assert!(error.to_string().contains("timed out"));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_password_eval_takes_precedence() {
+        let mut routine = Routine::new("Notes".to_string(), "/tmp".to_string());
+        assert!(routine.crypt.password().is_err());
+
+        routine.crypt.password = Some("plain".into());
+        assert_eq!(routine.crypt.password().unwrap().as_str(), "plain");
+
+        routine.crypt.password_eval = Some("echo evaluated".to_string());
+        assert_eq!(routine.crypt.password().unwrap().as_str(), "evaluated");
+
+        // A failing command does not silently fall back to the plain text password
+        routine.crypt.password_eval = Some("false".to_string());
+        assert!(routine.crypt.password().is_err());
+    }
+
+    #[test]
+    fn test_secrets_serialize_masked_only_when_asked() {
+        let secret = Secret::from("hunter2");
+
+        assert_eq!(serde_json::to_string(&secret).unwrap(), "\"hunter2\"");
+        assert_eq!(masked(|| serde_json::to_string(&secret).unwrap()), format!("\"{}\"", MASK));
+        assert_eq!(serde_json::to_string(&secret).unwrap(), "\"hunter2\"");
+    }
+}
@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod state_tests {
+    use crate::library::routine::Routine;
+    use crate::library::state::{RoutineState, StateStore};
+    use chrono::{Duration, TimeZone, Utc};
+    use git2::Oid;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_record_counts_failures_until_a_success() {
+        let now = Utc.with_ymd_and_hms(2024, 12, 15, 12, 0, 0).unwrap();
+        let commit = Oid::from_str("0123456789abcdef0123456789abcdef01234567").unwrap();
+        let offline: Box<dyn std::error::Error> = "offline".into();
+        let mut state = RoutineState::default();
+
+        state.record(now, Ok(commit));
+        state.record(now + Duration::hours(1), Err(&*offline));
+        state.record(now + Duration::hours(2), Err(&*offline));
+
+        assert_eq!(state.last_success, Some(now));
+        assert_eq!(state.last_attempt, Some(now + Duration::hours(2)));
+        assert_eq!(state.last_error.as_deref(), Some("offline"));
+        assert_eq!(state.last_commit, Some(commit.to_string()));
+        assert_eq!(state.consecutive_failures, 2);
+
+        state.record(now + Duration::hours(3), Ok(commit));
+        assert_eq!(state.last_success, Some(now + Duration::hours(3)));
+        assert!(state.last_error.is_none());
+        assert_eq!(state.consecutive_failures, 0);
+    }
+
+    #[test]
+    fn test_store_round_trips_state_per_routine() {
+        let dir = tempdir().unwrap();
+        let store = StateStore::at(dir.path().join("state"));
+        let notes_path = dir.path().join("ACT-Notes.toml");
+        let music_path = dir.path().join("ACT-Music.toml");
+
+        // Routines that were never synced have an empty state
+        assert_eq!(store.load(&notes_path).unwrap(), RoutineState::default());
+
+        let offline: Box<dyn std::error::Error> = "offline".into();
+        let mut state = RoutineState::default();
+        state.record(Utc.with_ymd_and_hms(2024, 12, 15, 12, 0, 0).unwrap(), Err(&*offline));
+        store.save(&notes_path, &state).unwrap();
+
+        assert_eq!(store.load(&notes_path).unwrap(), state);
+        assert_eq!(store.load(&music_path).unwrap(), RoutineState::default());
+
+        // Saving replaces the previous state without leaving temporary files behind
+        state.consecutive_failures = 0;
+        store.save(&notes_path, &state).unwrap();
+        assert_eq!(store.load(&notes_path).unwrap(), state);
+        assert_eq!(std::fs::read_dir(dir.path().join("state")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_last_sync_falls_back_to_routine_file() {
+        let mut routine = Routine::new("Notes".to_string(), "/path/to/notes".to_string());
+        routine.base.interval.last_sync_time = Some("2024-12-13T10:00:00Z".to_string());
+        let mut state = RoutineState::default();
+
+        let written = Utc.with_ymd_and_hms(2024, 12, 13, 10, 0, 0).unwrap();
+        assert_eq!(state.last_sync(&routine.base.interval), Some(written));
+
+        state.last_success = Some(written + Duration::days(1));
+        assert_eq!(state.last_sync(&routine.base.interval), state.last_success);
+    }
+}